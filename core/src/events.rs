@@ -0,0 +1,55 @@
+//! Events describing what happened during a transition, so that clients and loggers don't have to
+//! diff game states to find out.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Card, PlayerID};
+
+/// Something which happened in the game. Events only carry public information, so they are safe to
+/// show to every player.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum GameEvent {
+    /// Before the game starts, a player marked themselves as ready or not.
+    PlayerReady { player_id: PlayerID, ready: bool },
+    /// A new round started with everyone's cards in hand.
+    RoundStarted { starting_player: PlayerID },
+    /// A player ran out of time, and the action which follows was taken on their behalf.
+    TurnTimedOut { player_id: PlayerID },
+    /// A player placed a card face-down on their stack.
+    CardPlaced { player_id: PlayerID },
+    /// A player bid that they can flip `amount` flowers.
+    BidMade { player_id: PlayerID, amount: u8 },
+    /// A player dropped out of the bidding.
+    Passed { player_id: PlayerID },
+    /// Bidding finished, and the `selector` must now flip `goal` flowers.
+    ChallengeStarted { selector: PlayerID, goal: u8 },
+    /// The top card of `from_player`'s stack was flipped.
+    CardRevealed { from_player: PlayerID, card: Card },
+    /// The selector flipped enough flowers.
+    ChallengeWon { selector: PlayerID },
+    /// The selector flipped `skull_owner`'s skull.
+    ChallengeFailed {
+        selector: PlayerID,
+        skull_owner: PlayerID,
+    },
+    /// The selector gave up on the challenge, and must choose one of their cards to lose.
+    ChallengeConceded { selector: PlayerID },
+    /// The failed selector lost one of their cards.
+    CardDiscarded { player_id: PlayerID },
+    /// A player lost their last card.
+    PlayerEliminated { player_id: PlayerID },
+    /// A player who joined during the previous round was dealt in.
+    PlayerJoined { player_id: PlayerID },
+    /// A player resigned from the game. If a round was in progress, it carries on without them.
+    PlayerResigned { player_id: PlayerID },
+    /// A player other than the selector conceded the game, which took them out as if they had
+    /// resigned.
+    PlayerConceded { player_id: PlayerID },
+    /// A player was removed from the game with `Game::remove_player`.
+    PlayerRemoved { player_id: PlayerID },
+    /// The game is over.
+    GameWon { winner: PlayerID },
+}
@@ -0,0 +1,1732 @@
+//! The top-level game engine, which routes player actions to whichever phase the game is in.
+
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::events::GameEvent;
+use crate::game_states::bidding::{Bid, BiddingError, BiddingResult};
+use crate::game_states::discard::{Discard, DiscardError, DiscardResult};
+use crate::game_states::game_over::GameOver;
+use crate::game_states::initialize::Initialize;
+use crate::game_states::placement::{Placement, PlacementError};
+use crate::game_states::selection::{SelectionError, SelectionResult};
+use crate::game_states::GameState;
+use crate::settings::{GameSettings, Scoring, SettingsError, TimeoutPolicy};
+use crate::spec::{self, ActionKind, Phase};
+use crate::types::{
+    ActiveStatus, Card, Hand, HandError, PlayerError, PlayerID, Players, Presence, Profile,
+    Timestamp,
+};
+use crate::views::{ObserverView, PlayerView, Visibility};
+
+/// Everything a player can do during a game, independent of the current phase.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Action {
+    /// Place a card from the player's hand on top of their stack.
+    PlaceCard(Card),
+    /// Bid that the player can flip this many flowers. Opens the bidding if made during the
+    /// placement phase.
+    Bid(u8),
+    /// Drop out of the bidding.
+    Pass,
+    /// Flip the top card of the provided player's stack.
+    Flip(PlayerID),
+    /// After a failed challenge, discard the selector's card at this index in
+    /// `Discard::selector_cards`.
+    Discard(usize),
+    /// Deal everyone in and start the first round.
+    StartGame,
+    /// Before the game starts, mark whether the player is ready. See `Initialize::set_ready`.
+    Ready(bool),
+    /// Leave the game. Before it starts, the player becomes an observer. During a round, the round
+    /// carries on without the player or their cards, as with `Game::remove_player`, but they stay
+    /// on as an observer. If only one player is left with cards, they win.
+    Resign,
+    /// Give up on the current challenge, which fails as though the selector had flipped their own
+    /// skull: they choose one of their cards to lose. Anyone other than the selector concedes the
+    /// whole game, which takes them out as with `Action::Resign` but is announced as
+    /// `GameEvent::PlayerConceded`.
+    Concede,
+}
+
+/// A game in progress. The current phase keeps track of the players, and each phase hands them on
+/// to the next so that scores are carried across rounds.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Game {
+    state: GameState,
+    /// Seed for the next random decision (e.g. shuffling a failed selector's cards), so that a game
+    /// plays out identically given the same seed and actions.
+    seed: u64,
+    rounds_played: u32,
+    /// When the current player's turn runs out, if the game is timed.
+    deadline: Option<Timestamp>,
+    /// Players who joined mid-round, and are observing until the next round starts.
+    joining: Vec<PlayerID>,
+}
+
+impl Game {
+    #[must_use]
+    pub fn new(players: Players) -> Self {
+        Self::with_seed(players, rand::thread_rng().gen())
+    }
+
+    #[must_use]
+    pub fn with_seed(players: Players, seed: u64) -> Self {
+        Self::from_state(GameState::Initialize(Initialize::new(players)), seed)
+    }
+
+    fn from_state(state: GameState, seed: u64) -> Self {
+        Self {
+            state,
+            seed,
+            rounds_played: 0,
+            deadline: None,
+            joining: vec![],
+        }
+    }
+
+    /// Set up a game which is played with the provided rule options. Returns an error if they
+    /// don't make a playable game; see `GameSettings::validate`.
+    pub fn with_settings(
+        players: Players,
+        settings: GameSettings,
+        seed: u64,
+    ) -> Result<Self, SettingsError> {
+        let initialize = Initialize::new(players).with_settings(settings)?;
+        Ok(Self::from_state(GameState::Initialize(initialize), seed))
+    }
+
+    /// Reconstruct a game by replaying `actions`, each paired with the player who took it, from
+    /// `initial`. `seed` must be the seed of the original game when it was in `initial`, as
+    /// returned by `Game::seed`. Only `Action`s are replayed, so a game which changed through
+    /// anything else, such as `Game::join` or `Game::remove_player` mid-game, can't be
+    /// reconstructed this way.
+    pub fn replay(
+        initial: Initialize,
+        seed: u64,
+        actions: &[(PlayerID, Action)],
+    ) -> Result<Game, ReplayError> {
+        let mut game = Self::from_state(GameState::Initialize(initial), seed);
+        for (index, (player_id, action)) in actions.iter().enumerate() {
+            game = game
+                .apply(*player_id, *action)
+                .map_err(|error| ReplayError::ActionFailed { index, error })?
+                .0;
+        }
+        Ok(game)
+    }
+
+    pub fn players(&self) -> &'_ Players {
+        self.state.players()
+    }
+
+    /// The seed for the game's next random decision. Record this alongside the actions taken to be
+    /// able to `replay` the game.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of rounds which have finished, i.e. ended with a challenge being won or a card
+    /// being discarded.
+    pub fn rounds_played(&self) -> u32 {
+        self.rounds_played
+    }
+
+    pub fn state(&self) -> &'_ GameState {
+        &self.state
+    }
+
+    /// The parts of the game which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        self.state.view_for(viewer)
+    }
+
+    /// The game as seen by a spectator with the given visibility.
+    pub fn observer_view(&self, visibility: Visibility) -> ObserverView {
+        self.state.observer_view(visibility)
+    }
+
+    /// Start another game with the same players once this one is over. Scores are reset and
+    /// everyone is dealt a fresh hand. See `GameOver::rematch`. If `GameSettings::require_ready`
+    /// is set, the game goes back to the `Initialize` phase until everyone is ready again.
+    pub fn rematch(&self, rotate_starting_player: bool) -> Result<Game, GameError> {
+        let initialize = match &self.state {
+            GameState::GameOver(g) => g.rematch(rotate_starting_player)?,
+            _ => return Err(GameError::InvalidAction),
+        };
+        let state = if initialize.settings().require_ready {
+            GameState::Initialize(initialize)
+        } else {
+            GameState::Placement(initialize.start_game()?)
+        };
+        Ok(Self::from_state(state, self.seed))
+    }
+
+    /// Add a player to the game (by name), as with `Initialize::add_player`. Once the game has
+    /// started, they observe until the next round starts, when they are dealt a fresh hand. If the
+    /// game is over, they stay an observer. Joining again under the same name returns the same ID,
+    /// whether the player is already playing, waiting to be dealt in, or observing.
+    pub fn join(&self, name: String) -> Result<(Game, PlayerID), GameError> {
+        let mut game = self.clone();
+        let player_id = match &self.state {
+            GameState::Initialize(i) => {
+                let (i, player_id) = i.add_player(name)?;
+                game.state = GameState::Initialize(i);
+                return Ok((game, player_id));
+            }
+            state => {
+                let observer = state
+                    .players()
+                    .observers()
+                    .find(|p| p.name() == name)
+                    .map(|p| p.player_id());
+                let (players, player_id) = match observer {
+                    // They're already waiting for the next round.
+                    Some(player_id) if self.joining.contains(&player_id) => {
+                        return Ok((game, player_id))
+                    }
+                    Some(player_id) => (state.players().clone(), player_id),
+                    None => {
+                        let (players, player_id) = state.players().add_player(name)?;
+                        if state.players().player(player_id).is_ok() {
+                            // They're already playing.
+                            return Ok((game, player_id));
+                        }
+                        (players.make_player_into_observer(player_id)?, player_id)
+                    }
+                };
+                if !matches!(state, GameState::GameOver(_))
+                    && players.player_ids().len() + self.joining.len() + 1
+                        > state.settings().max_players
+                {
+                    return Err(PlayerError::TooManyPlayers.into());
+                }
+                *game.state.players_mut() = players;
+                player_id
+            }
+        };
+        if !matches!(game.state, GameState::GameOver(_)) {
+            game.joining.push(player_id);
+        }
+        Ok((game, player_id))
+    }
+
+    /// When the current player's turn runs out, if the game is timed. See `Game::apply_at`.
+    pub fn deadline(&self) -> Option<Timestamp> {
+        self.deadline
+    }
+
+    /// Returns the current player if their turn has run out by `now`, either because their
+    /// deadline has passed or because they've been disconnected for longer than
+    /// `GameSettings::disconnect_time_limit`.
+    pub fn expire_turn(&self, now: Timestamp) -> Option<PlayerID> {
+        let player_id = self.state.current_player()?;
+        let presence = self.players().player(player_id).ok()?.presence();
+        let timed_out = matches!(self.deadline, Some(deadline) if now >= deadline);
+        let disconnected = match (presence, self.state.settings().disconnect_time_limit) {
+            (Presence::Disconnected { since }, Some(limit)) => {
+                now.0 >= since.0.saturating_add(limit)
+            }
+            _ => false,
+        };
+        if timed_out || disconnected {
+            Some(player_id)
+        } else {
+            None
+        }
+    }
+
+    /// Record whether the provided player or observer is connected.
+    pub fn set_presence(&self, player_id: PlayerID, presence: Presence) -> Result<Game, GameError> {
+        let mut game = self.clone();
+        let players = game.state.players_mut();
+        *players = players.set_presence(player_id, presence)?;
+        Ok(game)
+    }
+
+    /// Change the name of the provided player or observer. See `Players::rename_player`.
+    pub fn rename_player(&self, player_id: PlayerID, name: String) -> Result<Game, GameError> {
+        let mut game = self.clone();
+        let players = game.state.players_mut();
+        *players = players.rename_player(player_id, name)?;
+        Ok(game)
+    }
+
+    /// Replace the cosmetic details of the provided player or observer. See
+    /// `Players::update_profile`.
+    pub fn update_profile(&self, player_id: PlayerID, profile: Profile) -> Result<Game, GameError> {
+        let mut game = self.clone();
+        let players = game.state.players_mut();
+        *players = players.update_profile(player_id, profile)?;
+        Ok(game)
+    }
+
+    /// If the current player has run out of time by `now`, act on their behalf according to
+    /// `GameSettings::timeout_policy`. Returns `TurnNotExpired` otherwise.
+    pub fn apply_timeout(&self, now: Timestamp) -> Result<(Game, Vec<GameEvent>), GameError> {
+        let player_id = self.expire_turn(now).ok_or(GameError::TurnNotExpired)?;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let action = match (self.state.settings().timeout_policy, &self.state) {
+            (TimeoutPolicy::AutoAction, GameState::Placement(p)) => match p.hand(player_id) {
+                Some(hand) => Action::PlaceCard(
+                    hand.cards()
+                        .choose(&mut rng)
+                        .ok_or(GameError::InvalidAction)?,
+                ),
+                None => Action::Bid(1),
+            },
+            (TimeoutPolicy::AutoAction, GameState::Bidding(_)) => Action::Pass,
+            (TimeoutPolicy::AutoAction, GameState::Selection(s)) => {
+                let drawable = s.drawable_players();
+                if drawable.contains(&player_id) {
+                    Action::Flip(player_id)
+                } else {
+                    Action::Flip(*drawable.choose(&mut rng).ok_or(GameError::InvalidAction)?)
+                }
+            }
+            (TimeoutPolicy::AutoAction, GameState::Discard(d)) => {
+                if d.selector_cards().is_empty() {
+                    return Err(GameError::InvalidAction);
+                }
+                Action::Discard(rng.gen_range(0, d.selector_cards().len()))
+            }
+            (TimeoutPolicy::Forfeit, _) => Action::Resign,
+            _ => return Err(GameError::InvalidAction),
+        };
+        let (game, events) = self.apply_at(player_id, action, now)?;
+        let events = std::iter::once(GameEvent::TurnTimedOut { player_id })
+            .chain(events)
+            .collect();
+        Ok((game, events))
+    }
+
+    /// Apply the action taken by `player_id` to the current phase, returning the resulting game
+    /// and the events which occurred. Turn deadlines are cleared once a different player is up;
+    /// use `Game::apply_at` to track them.
+    pub fn apply(
+        &self,
+        player_id: PlayerID,
+        action: Action,
+    ) -> Result<(Game, Vec<GameEvent>), GameError> {
+        self.apply_with_clock(player_id, action, None)
+    }
+
+    /// Apply an action taken at `now`, as with `Game::apply`. If `GameSettings::turn_time_limit`
+    /// is set, whenever a different player is up their deadline is set relative to `now`.
+    pub fn apply_at(
+        &self,
+        player_id: PlayerID,
+        action: Action,
+        now: Timestamp,
+    ) -> Result<(Game, Vec<GameEvent>), GameError> {
+        self.apply_with_clock(player_id, action, Some(now))
+    }
+
+    fn apply_with_clock(
+        &self,
+        player_id: PlayerID,
+        action: Action,
+        now: Option<Timestamp>,
+    ) -> Result<(Game, Vec<GameEvent>), GameError> {
+        let mut players = self.state.players().clone();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut rounds_played = self.rounds_played;
+        let mut events = vec![];
+        let kind = ActionKind::of(&action);
+        let state = match (&self.state, action) {
+            (GameState::Initialize(i), Action::StartGame) => {
+                // Only someone sitting at the table can start the game.
+                i.players().player(player_id)?;
+                let p = i.start_game()?;
+                events.push(GameEvent::RoundStarted {
+                    starting_player: p.current_player(),
+                });
+                GameState::Placement(p)
+            }
+            (GameState::Placement(p), Action::PlaceCard(card)) => {
+                let p = p.place_card(player_id, card)?;
+                events.push(GameEvent::CardPlaced { player_id });
+                GameState::Placement(p)
+            }
+            (GameState::Placement(p), Action::Bid(amount)) => {
+                let b = p.bid(player_id, amount)?;
+                events.push(GameEvent::BidMade { player_id, amount });
+                GameState::Bidding(b)
+            }
+            (GameState::Bidding(b), Action::Bid(_)) | (GameState::Bidding(b), Action::Pass) => {
+                let (bid, event) = match action {
+                    Action::Bid(amount) => (
+                        Bid::Amount(amount),
+                        GameEvent::BidMade { player_id, amount },
+                    ),
+                    _ => (Bid::Pass, GameEvent::Passed { player_id }),
+                };
+                let result = b.make_bid(player_id, bid)?;
+                events.push(event);
+                after_bidding(result, &mut events)
+            }
+            (GameState::Selection(s), Action::Flip(from_player)) => {
+                if player_id != s.selector() {
+                    return Err(GameError::NotSelector);
+                }
+                match s.clone().pick_card(from_player)? {
+                    SelectionResult::More(s) => {
+                        events.push(GameEvent::CardRevealed {
+                            from_player,
+                            card: Card::Flower,
+                        });
+                        GameState::Selection(s)
+                    }
+                    SelectionResult::Complete(selector) => {
+                        events.push(GameEvent::CardRevealed {
+                            from_player,
+                            card: Card::Flower,
+                        });
+                        challenge_won(
+                            selector,
+                            s.returned_hands()?,
+                            *s.settings(),
+                            &mut players,
+                            &mut rounds_played,
+                            &mut events,
+                        )?
+                    }
+                    SelectionResult::Failed(skull_owner) => {
+                        events.push(GameEvent::CardRevealed {
+                            from_player,
+                            card: Card::Skull,
+                        });
+                        events.push(GameEvent::ChallengeFailed {
+                            selector: s.selector(),
+                            skull_owner,
+                        });
+                        GameState::Discard(Discard::new(
+                            players.clone(),
+                            s.returned_hands()?,
+                            s.selector(),
+                            skull_owner,
+                            *s.settings(),
+                            &mut rng,
+                        )?)
+                    }
+                    SelectionResult::Voided(_) => return Err(GameError::InvalidAction),
+                }
+            }
+            (GameState::Selection(s), Action::Concede) if s.selector() == player_id => {
+                events.push(GameEvent::ChallengeConceded {
+                    selector: s.selector(),
+                });
+                GameState::Discard(Discard::new(
+                    players.clone(),
+                    s.returned_hands()?,
+                    s.selector(),
+                    s.selector(),
+                    *s.settings(),
+                    &mut rng,
+                )?)
+            }
+            (GameState::Discard(d), Action::Discard(index)) => {
+                let result = d.discard(player_id, index)?;
+                events.push(GameEvent::CardDiscarded {
+                    player_id: d.selector(),
+                });
+                if d.selector_cards().len() == 1 {
+                    events.push(GameEvent::PlayerEliminated {
+                        player_id: d.selector(),
+                    });
+                }
+                rounds_played += 1;
+                after_discard(result, &players, rounds_played, &mut events)
+            }
+            (GameState::Initialize(i), Action::Ready(ready)) => {
+                let i = i.set_ready(player_id, ready)?;
+                events.push(GameEvent::PlayerReady { player_id, ready });
+                GameState::Initialize(i)
+            }
+            (GameState::Initialize(i), Action::Resign)
+            | (GameState::Initialize(i), Action::Concede) => {
+                let i = i.resign(player_id)?;
+                events.push(left_game(player_id, action));
+                GameState::Initialize(i)
+            }
+            (_, Action::Resign) | (_, Action::Concede) => {
+                // Players who aren't in the game or have been eliminated can't resign.
+                if !self.returned_hands()?.contains_key(&player_id) {
+                    return Err(GameError::InvalidAction);
+                }
+                events.push(left_game(player_id, action));
+                self.depart(player_id, &mut rounds_played, &mut events)?
+            }
+            _ => return Err(GameError::InvalidAction),
+        };
+        debug_assert!(
+            spec::is_allowed(Phase::of(&self.state), kind, Phase::of(&state)),
+            "{:?} led from {:?} to {:?}, which isn't in spec::TRANSITIONS",
+            kind,
+            Phase::of(&self.state),
+            Phase::of(&state),
+        );
+        self.finish(state, events, rounds_played, rng.gen(), now)
+    }
+
+    /// Remove a player or observer from the game entirely, e.g. if they've left for good. Before
+    /// the game starts or once it's over, they're just taken off the roster, as are observers and
+    /// anyone waiting to be dealt in. Mid-round, the round carries
+    /// on without them as far as possible: see the phases' `remove_player` methods. Once only one
+    /// player has cards left, they win. `now` is used for turn deadlines, as with
+    /// `Game::apply_at`.
+    pub fn remove_player(
+        &self,
+        player_id: PlayerID,
+        now: Option<Timestamp>,
+    ) -> Result<(Game, Vec<GameEvent>), GameError> {
+        let mut rounds_played = self.rounds_played;
+        let mut events = vec![GameEvent::PlayerRemoved { player_id }];
+        let mut state = match self.state {
+            GameState::Initialize(_) | GameState::GameOver(_) => self.state.clone(),
+            // Observers, including anyone waiting to be dealt in, aren't part of the round.
+            _ if self.players().player(player_id).is_err() => self.state.clone(),
+            _ => self.depart(player_id, &mut rounds_played, &mut events)?,
+        };
+        // Having left play, they're only observing by now.
+        let players = state.players_mut();
+        *players = players.remove_player(player_id)?;
+        let mut game = self.clone();
+        game.joining.retain(|p| *p != player_id);
+        game.finish(state, events, rounds_played, self.seed, now)
+    }
+
+    /// Continue the current round without `player_id`, who has left mid-round and stays on as an
+    /// observer.
+    fn depart(
+        &self,
+        player_id: PlayerID,
+        rounds_played: &mut u32,
+        events: &mut Vec<GameEvent>,
+    ) -> Result<GameState, GameError> {
+        let players = self.players().make_player_into_observer(player_id)?;
+        let mut hands = self.returned_hands()?;
+        hands.remove(&player_id);
+        let settings = *self.state.settings();
+        if let [winner] = hands.keys().copied().collect::<Vec<_>>().as_slice() {
+            events.push(GameEvent::GameWon { winner: *winner });
+            return Ok(GameState::GameOver(GameOver::new(
+                players.clone(),
+                *winner,
+                settings,
+            )));
+        }
+        Ok(match &self.state {
+            GameState::Placement(p) => GameState::Placement(p.remove_player(player_id)?),
+            GameState::Bidding(b) => after_bidding(b.remove_player(player_id)?, events),
+            GameState::Selection(s) if s.selector() == player_id => {
+                // There's no challenge without the selector, so the round is abandoned.
+                let starting_player = self
+                    .players()
+                    .next_active_player(player_id)
+                    .ok_or(GameError::InvalidAction)?
+                    .player_id();
+                let p = Placement::new_round(starting_player, players.clone(), hands, settings);
+                events.push(GameEvent::RoundStarted {
+                    starting_player: p.current_player(),
+                });
+                GameState::Placement(p)
+            }
+            GameState::Selection(s) => match s.remove_player(player_id)? {
+                SelectionResult::More(s) => GameState::Selection(s),
+                SelectionResult::Voided(p) => {
+                    events.push(GameEvent::RoundStarted {
+                        starting_player: p.current_player(),
+                    });
+                    GameState::Placement(p)
+                }
+                _ => return Err(GameError::InvalidAction),
+            },
+            GameState::Discard(d) => {
+                let result = d.remove_player(player_id)?;
+                // The selector loses a card on behalf of a chooser who left.
+                if player_id == d.chooser() && player_id != d.selector() {
+                    events.push(GameEvent::CardDiscarded {
+                        player_id: d.selector(),
+                    });
+                    if d.selector_cards().len() == 1 {
+                        events.push(GameEvent::PlayerEliminated {
+                            player_id: d.selector(),
+                        });
+                    }
+                    *rounds_played += 1;
+                }
+                after_discard(result, &players, *rounds_played, events)
+            }
+            _ => return Err(GameError::InvalidAction),
+        })
+    }
+
+    /// Every player's hand if the current round were to end now. Fails outside of a round.
+    fn returned_hands(&self) -> Result<HashMap<PlayerID, Hand>, GameError> {
+        Ok(match &self.state {
+            GameState::Placement(p) => p.returned_hands()?,
+            GameState::Bidding(b) => b.returned_hands()?,
+            GameState::Selection(s) => s.returned_hands()?,
+            GameState::Discard(d) => d.returned_hands().clone(),
+            _ => return Err(GameError::InvalidAction),
+        })
+    }
+
+    /// Deal in anyone waiting to join, keep track of who's out and when the current turn runs
+    /// out, and put together the resulting game.
+    fn finish(
+        &self,
+        state: GameState,
+        mut events: Vec<GameEvent>,
+        rounds_played: u32,
+        seed: u64,
+        now: Option<Timestamp>,
+    ) -> Result<(Game, Vec<GameEvent>), GameError> {
+        // Anyone who joined during the round is dealt in once the next one starts.
+        let mut joining = self.joining.clone();
+        let mut state = match state {
+            GameState::Placement(p)
+                if !joining.is_empty()
+                    && events
+                        .iter()
+                        .any(|e| matches!(e, GameEvent::RoundStarted { .. })) =>
+            {
+                let mut players = p.players().clone();
+                let mut hands = p.returned_hands()?;
+                for player_id in joining.drain(..) {
+                    players = players.make_observer_into_player(player_id)?;
+                    hands.insert(player_id, p.settings().starting_hand);
+                    events.push(GameEvent::PlayerJoined { player_id });
+                }
+                GameState::Placement(Placement::new_round(
+                    p.current_player(),
+                    players,
+                    hands,
+                    *p.settings(),
+                ))
+            }
+            state @ GameState::GameOver(_) => {
+                // Nobody is dealt in once the game is over, so they stay observers.
+                joining.clear();
+                state
+            }
+            state => state,
+        };
+        // Keep track of who is out of the game, so that turn order can skip them. Players who
+        // resigned or conceded are observers by now, so they're skipped anyway.
+        for event in &events {
+            if let GameEvent::PlayerEliminated { player_id } = *event {
+                let players = state.players_mut();
+                *players = players.set_status(player_id, ActiveStatus::Eliminated)?;
+            }
+        }
+        // A player's clock restarts whenever they're up again, including when a new round or
+        // phase starts with the player who was already acting.
+        let same_turn = state.current_player() == self.state.current_player()
+            && Phase::of(&state) == Phase::of(&self.state)
+            && !events
+                .iter()
+                .any(|e| matches!(e, GameEvent::RoundStarted { .. }));
+        let deadline = if same_turn {
+            self.deadline
+        } else {
+            match (
+                now,
+                state.settings().turn_time_limit,
+                state.current_player(),
+            ) {
+                (Some(now), Some(limit), Some(_)) => Some(Timestamp(now.0.saturating_add(limit))),
+                _ => None,
+            }
+        };
+        let game = Self {
+            state,
+            seed,
+            rounds_played,
+            deadline,
+            joining,
+        };
+        Ok((game, events))
+    }
+}
+
+/// The event for a player resigning or conceding the game.
+fn left_game(player_id: PlayerID, action: Action) -> GameEvent {
+    match action {
+        Action::Concede => GameEvent::PlayerConceded { player_id },
+        _ => GameEvent::PlayerResigned { player_id },
+    }
+}
+
+/// Move on from the bidding, announcing the challenge if it's starting.
+fn after_bidding(result: BiddingResult, events: &mut Vec<GameEvent>) -> GameState {
+    match result {
+        BiddingResult::KeepBidding(b) => GameState::Bidding(b),
+        BiddingResult::ResumePlacement(p) => GameState::Placement(p),
+        BiddingResult::StartSelection(s) => {
+            events.push(GameEvent::ChallengeStarted {
+                selector: s.selector(),
+                goal: s.goal(),
+            });
+            GameState::Selection(s)
+        }
+    }
+}
+
+/// Score a won challenge. Unless that won the selector the game, all cards go back to their
+/// owners and the selector starts the next round.
+fn challenge_won(
+    selector: PlayerID,
+    hands: HashMap<PlayerID, Hand>,
+    settings: GameSettings,
+    players: &mut Players,
+    rounds_played: &mut u32,
+    events: &mut Vec<GameEvent>,
+) -> Result<GameState, GameError> {
+    events.push(GameEvent::ChallengeWon { selector });
+    let winner = match settings.scoring {
+        Scoring::Challenges { wins_required } => {
+            let (new_players, winner) = players.increment_score(selector, wins_required)?;
+            *players = new_players;
+            winner
+        }
+        Scoring::Points {
+            points_per_challenge,
+            ..
+        } => {
+            *players = players.award_points(selector, points_per_challenge)?;
+            None
+        }
+    };
+    *rounds_played += 1;
+    Ok(
+        match winner.or_else(|| points_winner(players, &settings, *rounds_played)) {
+            Some(winner) => {
+                events.push(GameEvent::GameWon { winner });
+                GameState::GameOver(GameOver::new(players.clone(), winner, settings))
+            }
+            None => {
+                let p = Placement::new_round(selector, players.clone(), hands, settings);
+                events.push(GameEvent::RoundStarted {
+                    starting_player: p.current_player(),
+                });
+                GameState::Placement(p)
+            }
+        },
+    )
+}
+
+/// Move on from a discard: the next round starts, unless only one player has cards left or the
+/// final round has been played when playing for points.
+fn after_discard(
+    result: DiscardResult,
+    players: &Players,
+    rounds_played: u32,
+    events: &mut Vec<GameEvent>,
+) -> GameState {
+    match result {
+        DiscardResult::NextRound(p) => match points_winner(players, p.settings(), rounds_played) {
+            Some(winner) => {
+                events.push(GameEvent::GameWon { winner });
+                GameState::GameOver(GameOver::new(players.clone(), winner, *p.settings()))
+            }
+            None => {
+                events.push(GameEvent::RoundStarted {
+                    starting_player: p.current_player(),
+                });
+                GameState::Placement(p)
+            }
+        },
+        DiscardResult::GameOver(g) => {
+            events.push(GameEvent::GameWon { winner: g.winner() });
+            GameState::GameOver(g)
+        }
+        DiscardResult::Pending(d) => GameState::Discard(d),
+    }
+}
+
+/// When playing for points, the leader once the final round has been played.
+fn points_winner(
+    players: &Players,
+    settings: &GameSettings,
+    rounds_played: u32,
+) -> Option<PlayerID> {
+    match settings.scoring {
+        Scoring::Points { rounds, .. } if rounds_played >= rounds => players.points_leader(),
+        _ => None,
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GameError {
+    #[error("That action can't be taken in the current phase")]
+    InvalidAction,
+    #[error("Only the selector can flip cards")]
+    NotSelector,
+    #[error("The current player still has time left")]
+    TurnNotExpired,
+    #[error("Couldn't place card: {0}")]
+    PlacementError(#[from] PlacementError),
+    #[error("Couldn't bid: {0}")]
+    BiddingError(#[from] BiddingError),
+    #[error("Couldn't flip card: {0}")]
+    SelectionError(#[from] SelectionError),
+    #[error("Couldn't discard card: {0}")]
+    DiscardError(#[from] DiscardError),
+    #[error("Couldn't update player: {0}")]
+    PlayerError(#[from] PlayerError),
+    #[error("Couldn't return cards: {0}")]
+    HandError(#[from] HandError),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReplayError {
+    #[error("Couldn't replay action {index}: {error}")]
+    ActionFailed { index: usize, error: GameError },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Game, GameError, ReplayError};
+    use crate::events::GameEvent;
+    use crate::game_states::discard::DiscardError;
+    use crate::game_states::initialize::Initialize;
+    use crate::game_states::placement::PlacementError;
+    use crate::game_states::GameState;
+    use crate::settings::{GameSettings, Scoring, TimeoutPolicy};
+    use crate::types::{
+        ActiveStatus, Card, Hand, HandError, PlayerError, PlayerID, Players, Presence, Timestamp,
+    };
+
+    fn new_game() -> (Game, PlayerID, PlayerID, PlayerID) {
+        let players = Players::new();
+        let (players, p1) = players.add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let game = Game::new(players).apply(p2, Action::StartGame).unwrap().0;
+        (game, p1, p2, p3)
+    }
+
+    fn win_challenge(game: Game, p1: PlayerID, p2: PlayerID, p3: PlayerID) -> Game {
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        game.apply(p1, Action::Flip(p2)).unwrap().0
+    }
+
+    /// Have `p1` challenge and flip `p2`'s skull. `p1` places a flower if they have one left.
+    fn fail_challenge(game: Game, p1: PlayerID, p2: PlayerID, others: &[PlayerID]) -> Game {
+        let game = game
+            .apply(p1, Action::PlaceCard(Card::Flower))
+            .or_else(|_| game.apply(p1, Action::PlaceCard(Card::Skull)))
+            .unwrap()
+            .0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = others.iter().fold(game, |game, p| {
+            game.apply(*p, Action::PlaceCard(Card::Flower)).unwrap().0
+        });
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = others
+            .iter()
+            .fold(game, |game, p| game.apply(*p, Action::Pass).unwrap().0);
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = match game.state() {
+            GameState::Selection(_) => game.apply(p1, Action::Flip(p2)).unwrap().0,
+            _ => game,
+        };
+        let chooser = match game.state() {
+            GameState::Discard(d) => d.chooser(),
+            s => panic!("unexpected state {:?}", s),
+        };
+        game.apply(chooser, Action::Discard(0)).unwrap().0
+    }
+
+    #[test]
+    pub fn test_start_game() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let game = Game::new(players);
+        assert_eq!(
+            game.apply(p1, Action::StartGame).unwrap_err(),
+            GameError::PlayerError(PlayerError::NotEnoughPlayers)
+        );
+
+        let (game, ..) = new_game();
+        let game = Game::new(game.players().clone());
+        assert_eq!(
+            game.apply(PlayerID(999), Action::StartGame).unwrap_err(),
+            GameError::PlayerError(PlayerError::PlayerDoesntExist)
+        );
+        let game = game.join("p4".to_string()).unwrap().0;
+        let p4 = game.players().player_ids()[3];
+        let game = game.apply(p4, Action::Resign).unwrap().0;
+        assert_eq!(
+            game.apply(p4, Action::StartGame).unwrap_err(),
+            GameError::PlayerError(PlayerError::PlayerDoesntExist)
+        );
+
+        let (game, p1, _, _) = new_game();
+        assert!(matches!(game.state(), GameState::Placement(_)));
+        assert_eq!(
+            game.apply(p1, Action::StartGame).unwrap_err(),
+            GameError::InvalidAction
+        );
+    }
+
+    #[test]
+    pub fn test_successful_challenge() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        assert!(matches!(game.state(), GameState::Bidding(_)));
+
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        assert!(matches!(game.state(), GameState::Selection(_)));
+
+        assert_eq!(
+            game.apply(p2, Action::Flip(p2)).unwrap_err(),
+            GameError::NotSelector
+        );
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p2)).unwrap().0;
+        assert!(matches!(game.state(), GameState::Placement(_)));
+        assert_eq!(game.players().player(p1).unwrap().score, 1);
+    }
+
+    #[test]
+    pub fn test_game_over() {
+        let (game, p1, p2, p3) = new_game();
+        let game = win_challenge(game, p1, p2, p3);
+        let game = win_challenge(game, p1, p2, p3);
+        match game.state() {
+            GameState::GameOver(g) => {
+                assert_eq!(g.winner(), p1);
+                assert_eq!(g.winning_player().unwrap().name(), "p1");
+                assert_eq!(
+                    g.scores().collect::<Vec<_>>(),
+                    vec![(p1, 2), (p2, 0), (p3, 0)]
+                );
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+        assert_eq!(
+            game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap_err(),
+            GameError::InvalidAction
+        );
+    }
+
+    #[test]
+    pub fn test_rematch() {
+        let (game, p1, p2, p3) = new_game();
+        assert_eq!(game.rematch(false).unwrap_err(), GameError::InvalidAction);
+
+        let game = win_challenge(game, p1, p2, p3);
+        let game = win_challenge(game, p1, p2, p3);
+        assert!(matches!(game.state(), GameState::GameOver(_)));
+
+        let rematch = game.rematch(true).unwrap();
+        assert!(matches!(rematch.state(), GameState::Placement(_)));
+        assert_eq!(rematch.players().player_ids(), &[p2, p3, p1]);
+        assert!(rematch.players().players().all(|p| p.score() == 0));
+
+        let rematch = game.rematch(false).unwrap();
+        assert_eq!(rematch.players().player_ids(), &[p1, p2, p3]);
+    }
+
+    #[test]
+    pub fn test_ready_check() {
+        let (game, p1, p2, p3) = new_game();
+        let settings = GameSettings {
+            require_ready: true,
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        assert_eq!(
+            game.apply(p1, Action::StartGame).unwrap_err(),
+            GameError::PlayerError(PlayerError::PlayersNotReady)
+        );
+        let (game, events) = game.apply(p1, Action::Ready(true)).unwrap();
+        assert_eq!(
+            events,
+            vec![GameEvent::PlayerReady {
+                player_id: p1,
+                ready: true
+            }]
+        );
+        let game = game.apply(p2, Action::Ready(true)).unwrap().0;
+        let game = game.apply(p3, Action::Ready(true)).unwrap().0;
+        let game = game.apply(p1, Action::StartGame).unwrap().0;
+        assert_eq!(
+            game.apply(p1, Action::Ready(false)).unwrap_err(),
+            GameError::InvalidAction
+        );
+
+        // A rematch waits for everyone to be ready again.
+        let game = win_challenge(game, p1, p2, p3);
+        let game = win_challenge(game, p1, p2, p3);
+        match game.rematch(false).unwrap().state() {
+            GameState::Initialize(i) => assert!(!i.is_ready(p1)),
+            s => panic!("unexpected state {:?}", s),
+        }
+    }
+
+    #[test]
+    pub fn test_settings() {
+        let (game, p1, p2, p3) = new_game();
+        let settings = GameSettings {
+            min_players: 4,
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        assert_eq!(
+            game.apply(p1, Action::StartGame).unwrap_err(),
+            GameError::PlayerError(PlayerError::NotEnoughPlayers)
+        );
+
+        let settings = GameSettings {
+            speed_bidding: true,
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game.apply(p1, Action::StartGame).unwrap().0;
+        let game = win_challenge(game, p1, p2, p3);
+        let game = win_challenge(game, p1, p2, p3);
+        assert_eq!(game.rematch(false).unwrap().state().settings(), &settings);
+
+        // First to three.
+        let settings = GameSettings {
+            scoring: Scoring::Challenges { wins_required: 3 },
+            ..GameSettings::default()
+        };
+        let mut game = Game::with_settings(game.players().reset_all_scores(), settings, 0)
+            .unwrap()
+            .apply(p1, Action::StartGame)
+            .unwrap()
+            .0;
+        for _ in 0..2 {
+            game = win_challenge(game, p1, p2, p3);
+        }
+        assert!(matches!(game.state(), GameState::Placement(_)));
+        let game = win_challenge(game, p1, p2, p3);
+        assert!(matches!(game.state(), GameState::GameOver(g) if g.winner() == p1));
+
+        // Two-skull variant.
+        let settings = GameSettings {
+            starting_hand: Hand::with_counts(3, 2).unwrap(),
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game.apply(p1, Action::StartGame).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        assert_eq!(game.view_for(p1).hand, Hand::with_counts(3, 0));
+    }
+
+    #[test]
+    pub fn test_turn_deadlines() {
+        let (game, p1, p2, p3) = new_game();
+        let settings = GameSettings {
+            turn_time_limit: Some(1000),
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game
+            .apply_at(p1, Action::StartGame, Timestamp(0))
+            .unwrap()
+            .0;
+        assert_eq!(game.deadline(), Some(Timestamp(1000)));
+        assert_eq!(game.expire_turn(Timestamp(999)), None);
+        assert_eq!(game.expire_turn(Timestamp(1000)), Some(p1));
+
+        let game = game
+            .apply_at(p1, Action::PlaceCard(Card::Flower), Timestamp(500))
+            .unwrap()
+            .0;
+        assert_eq!(game.deadline(), Some(Timestamp(1500)));
+        assert_eq!(game.expire_turn(Timestamp(1200)), None);
+
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        assert_eq!(game.deadline(), None);
+
+        // Flipping several cards is all one turn, but winning the challenge starts a new round,
+        // which gets a fresh deadline even though the selector starts it.
+        let game = [
+            (p3, Action::PlaceCard(Card::Skull)),
+            (p1, Action::Bid(2)),
+            (p2, Action::Pass),
+            (p3, Action::Pass),
+        ]
+        .iter()
+        .zip((1..).map(|i| Timestamp(i * 100)))
+        .fold(game, |game, ((p, action), now)| {
+            game.apply_at(*p, *action, now).unwrap().0
+        });
+        assert_eq!(game.deadline(), Some(Timestamp(1400)));
+        let game = game
+            .apply_at(p1, Action::Flip(p1), Timestamp(500))
+            .unwrap()
+            .0;
+        assert_eq!(game.deadline(), Some(Timestamp(1400)));
+        let (game, events) = game.apply_at(p1, Action::Flip(p2), Timestamp(600)).unwrap();
+        assert!(events.contains(&GameEvent::RoundStarted {
+            starting_player: p1
+        }));
+        assert_eq!(game.deadline(), Some(Timestamp(1600)));
+    }
+
+    #[test]
+    pub fn test_presence() {
+        let (game, p1, _, _) = new_game();
+        let settings = GameSettings {
+            disconnect_time_limit: Some(100),
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game.apply(p1, Action::StartGame).unwrap().0;
+        assert_eq!(game.expire_turn(Timestamp(1000)), None);
+
+        let disconnected = Presence::Disconnected {
+            since: Timestamp(50),
+        };
+        let game = game.set_presence(p1, disconnected).unwrap();
+        assert_eq!(
+            game.view_for(p1).players.player(p1).unwrap().presence(),
+            disconnected
+        );
+        assert_eq!(game.expire_turn(Timestamp(149)), None);
+        assert_eq!(game.expire_turn(Timestamp(150)), Some(p1));
+
+        let game = game.set_presence(p1, Presence::Connected).unwrap();
+        assert_eq!(game.expire_turn(Timestamp(150)), None);
+    }
+
+    #[test]
+    pub fn test_timeouts() {
+        let (game, p1, p2, p3) = new_game();
+        let settings = GameSettings {
+            turn_time_limit: Some(1000),
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game
+            .apply_at(p1, Action::StartGame, Timestamp(0))
+            .unwrap()
+            .0;
+        assert_eq!(
+            game.apply_timeout(Timestamp(999)).unwrap_err(),
+            GameError::TurnNotExpired
+        );
+
+        let (game, events) = game.apply_timeout(Timestamp(1000)).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::TurnTimedOut { player_id: p1 },
+                GameEvent::CardPlaced { player_id: p1 }
+            ]
+        );
+        assert_eq!(game.expire_turn(Timestamp(2000)), Some(p2));
+
+        let game = game
+            .apply_at(p2, Action::PlaceCard(Card::Flower), Timestamp(1500))
+            .unwrap()
+            .0;
+        let game = game
+            .apply_at(p3, Action::PlaceCard(Card::Flower), Timestamp(1500))
+            .unwrap()
+            .0;
+        let game = game
+            .apply_at(p1, Action::Bid(1), Timestamp(1500))
+            .unwrap()
+            .0;
+        let (game, events) = game.apply_timeout(Timestamp(2500)).unwrap();
+        assert_eq!(events[1], GameEvent::Passed { player_id: p2 });
+        assert_eq!(game.expire_turn(Timestamp(3500)), Some(p3));
+
+        let settings = GameSettings {
+            timeout_policy: TimeoutPolicy::Forfeit,
+            ..settings
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game
+            .apply_at(p1, Action::StartGame, Timestamp(0))
+            .unwrap()
+            .0;
+        let (_, events) = game.apply_timeout(Timestamp(1000)).unwrap();
+        assert_eq!(events[1], GameEvent::PlayerResigned { player_id: p1 });
+    }
+
+    #[test]
+    pub fn test_resign() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let (game, events) = game.apply(p3, Action::Resign).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerResigned { player_id: p3 }]);
+        // The round carries on without them.
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.current_player(), p1);
+                assert_eq!(p.hand(p3), None);
+                assert_eq!(p.total_cards_placed(), 2);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+        assert_eq!(game.players().player_ids(), &[p1, p2]);
+        assert!(game.players().observers().any(|p| p.player_id() == p3));
+        assert_eq!(
+            game.apply(p3, Action::Resign).unwrap_err(),
+            GameError::InvalidAction
+        );
+
+        // The selector resigning abandons the challenge, leaving a single player.
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(1)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        assert!(matches!(game.state(), GameState::Selection(_)));
+        let (game, events) = game.apply(p1, Action::Resign).unwrap();
+        assert_eq!(events[1], GameEvent::GameWon { winner: p2 });
+        assert!(matches!(game.state(), GameState::GameOver(g) if g.winner() == p2));
+        assert_eq!(
+            game.apply(p2, Action::Resign).unwrap_err(),
+            GameError::InvalidAction
+        );
+
+        // A failed challenger still loses a card if a bystander resigns before the discard.
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p2)).unwrap().0;
+        let (game, events) = game.apply(p3, Action::Resign).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerResigned { player_id: p3 }]);
+        assert!(matches!(game.state(), GameState::Discard(d) if d.chooser() == p2));
+        let game = game.apply(p2, Action::Discard(0)).unwrap().0;
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.hand(p1).unwrap().cards().count(), 3);
+                assert_eq!(p.hand(p3), None);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // Anyone but the selector conceding mid-challenge resigns, and the challenge goes on.
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let (game, events) = game.apply(p2, Action::Concede).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerConceded { player_id: p2 }]);
+        match game.state() {
+            GameState::Selection(s) => {
+                assert_eq!(s.selector(), p1);
+                assert_eq!(s.goal(), 2);
+                assert_eq!(s.drawable_players(), vec![p1]);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // Conceding outside of a challenge takes the player out, the same as resigning.
+        let (game, p1, p2, p3) = new_game();
+        let (game, events) = game.apply(p2, Action::Concede).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerConceded { player_id: p2 }]);
+        assert!(game.players().observers().any(|p| p.player_id() == p2));
+
+        // The selector conceding fails the challenge, and they choose their own discard.
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let (game, events) = game.apply(p1, Action::Concede).unwrap();
+        assert_eq!(events, vec![GameEvent::ChallengeConceded { selector: p1 }]);
+        match game.state() {
+            GameState::Discard(d) => {
+                assert_eq!(d.chooser(), p1);
+                assert_eq!(d.selector_cards().len(), 4);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // Before the game starts, resigning players become observers too.
+        let (game, p1, p2, p3) = new_game();
+        let game = Game::new(game.players().clone());
+        let game = game.apply(p3, Action::Resign).unwrap().0;
+        assert_eq!(game.players().player_ids(), &[p1, p2]);
+        assert_eq!(game.players().observers().count(), 1);
+    }
+
+    #[test]
+    pub fn test_concede_anywhere() {
+        // Every phase in which anyone can concede, with the transition checked against the spec.
+        let (game, p1, p2, p3) = new_game();
+        assert!(game.apply(p2, Action::Concede).is_ok());
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        assert!(game.apply(p3, Action::Concede).is_ok());
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        // A bystander conceding mid-challenge leaves the challenge going.
+        let (conceded, _) = game.apply(p3, Action::Concede).unwrap();
+        assert!(matches!(conceded.state(), GameState::Selection(_)));
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p3)).unwrap().0;
+        assert!(matches!(game.state(), GameState::Discard(_)));
+        assert!(game.apply(p2, Action::Concede).is_ok());
+    }
+
+    #[test]
+    pub fn test_remove_player() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let (game, events) = game.remove_player(p3, None).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerRemoved { player_id: p3 }]);
+        assert_eq!(game.players().player_ids(), &[p1, p2]);
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.players(), game.players());
+                assert_eq!(p.current_player(), p2);
+                assert_eq!(p.total_cards_placed(), 1);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+        let (game, events) = game.remove_player(p2, None).unwrap();
+        assert_eq!(events[1], GameEvent::GameWon { winner: p1 });
+        assert!(matches!(game.state(), GameState::GameOver(g) if g.winner() == p1));
+        assert_eq!(
+            game.remove_player(p3, None).unwrap_err(),
+            GameError::PlayerError(PlayerError::PlayerDoesntExist)
+        );
+
+        // If the skull owner leaves before choosing, a card is discarded for them.
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p2)).unwrap().0;
+        assert!(matches!(game.state(), GameState::Discard(d) if d.chooser() == p2));
+        let (game, events) = game.remove_player(p2, None).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::PlayerRemoved { player_id: p2 },
+                GameEvent::CardDiscarded { player_id: p1 },
+                GameEvent::RoundStarted {
+                    starting_player: p1
+                },
+            ]
+        );
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.hand(p1).unwrap().cards().count(), 3);
+                assert_eq!(p.players().player_ids(), &[p1, p3]);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // If the goal is out of reach without a player's stack, the challenge is void.
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(3)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let (game, events) = game.remove_player(p3, None).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::PlayerRemoved { player_id: p3 },
+                GameEvent::RoundStarted {
+                    starting_player: p1
+                },
+            ]
+        );
+        assert_eq!(game.players().player(p1).unwrap().score(), 0);
+        assert_eq!(game.rounds_played(), 0);
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.hand(p1), Some(Hand::new()));
+                assert_eq!(p.hand(p2), Some(Hand::new()));
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // Without the selector, the next active player starts a new round.
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(1)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let (game, events) = game.remove_player(p1, None).unwrap();
+        assert_eq!(
+            events[1],
+            GameEvent::RoundStarted {
+                starting_player: p2
+            }
+        );
+        assert!(matches!(game.state(), GameState::Placement(p) if p.total_cards_placed() == 0));
+
+        // Before the game starts, players are just taken off the roster.
+        let game = Game::new(game.players().clone());
+        let (game, _) = game.remove_player(p3, None).unwrap();
+        assert_eq!(game.players().player_ids(), &[p2]);
+        assert!(matches!(game.state(), GameState::Initialize(i) if i.players() == game.players()));
+    }
+
+    #[test]
+    pub fn test_join() {
+        let (game, p1, p2, p3) = new_game();
+        let (game, p4) = game.join("p4".to_string()).unwrap();
+        assert!(game.players().player(p4).is_err());
+        assert!(game.players().observers().any(|p| p.player_id() == p4));
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.hand(p4), None);
+                assert!(p.players().observers().any(|p| p.player_id() == p4));
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+        // Joining again under the same name doesn't add anyone new.
+        let (game, same) = game.join("p1".to_string()).unwrap();
+        assert_eq!(same, p1);
+        let (game, same) = game.join("p4".to_string()).unwrap();
+        assert_eq!(same, p4);
+        assert_eq!(game.players().observers().count(), 1);
+
+        // Someone who leaves before they're dealt in is forgotten.
+        let (game, p5) = game.join("p5".to_string()).unwrap();
+        let (game, events) = game.remove_player(p5, None).unwrap();
+        assert_eq!(events, vec![GameEvent::PlayerRemoved { player_id: p5 }]);
+        assert!(game.players().observers().all(|p| p.player_id() != p5));
+
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let (game, events) = game.apply(p1, Action::Flip(p2)).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::PlayerJoined { player_id: p4 })
+        );
+        assert!(!events.contains(&GameEvent::PlayerJoined { player_id: p5 }));
+        assert_eq!(game.players().player_ids(), &[p1, p2, p3, p4]);
+        assert_eq!(game.players().observers().count(), 0);
+        match game.state() {
+            GameState::Placement(p) => {
+                assert_eq!(p.current_player(), p1);
+                assert_eq!(p.hand(p4), Some(Hand::new()));
+                assert_eq!(p.players().player(p1).unwrap().score(), 1);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+
+        // Joins still respect the table size.
+        let settings = GameSettings {
+            max_players: 3,
+            ..GameSettings::default()
+        };
+        let (game, ..) = new_game();
+        let game = Game::with_settings(game.players().clone(), settings, 0)
+            .unwrap()
+            .apply(p2, Action::StartGame)
+            .unwrap()
+            .0;
+        assert_eq!(
+            game.join("p4".to_string()).unwrap_err(),
+            GameError::PlayerError(PlayerError::TooManyPlayers)
+        );
+    }
+
+    #[test]
+    pub fn test_points_scoring() {
+        let (game, p1, p2, p3) = new_game();
+        let settings = GameSettings {
+            scoring: Scoring::Points {
+                points_per_challenge: 3,
+                rounds: 3,
+            },
+            ..GameSettings::default()
+        };
+        let game = Game::with_settings(game.players().clone(), settings, 0).unwrap();
+        let game = game.apply(p1, Action::StartGame).unwrap().0;
+        let game = win_challenge(game, p1, p2, p3);
+        let game = win_challenge(game, p1, p2, p3);
+        assert!(matches!(game.state(), GameState::Placement(_)));
+        assert_eq!(game.rounds_played(), 2);
+        assert_eq!(game.players().player(p1).unwrap().points(), 6);
+
+        let game = fail_challenge(game, p1, p2, &[p3]);
+        match game.state() {
+            GameState::GameOver(g) => {
+                assert_eq!(g.winner(), p1);
+                assert_eq!(g.leaderboard(), vec![(p1, 6), (p2, 0), (p3, 0)]);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+    }
+
+    #[test]
+    pub fn test_failed_challenge() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(3)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p3)).unwrap().0;
+        match game.state() {
+            GameState::Discard(d) => {
+                assert_eq!(d.chooser(), p3);
+                assert_eq!(d.selector_cards().len(), 4);
+            }
+            s => panic!("unexpected state {:?}", s),
+        }
+        assert_eq!(
+            game.apply(p1, Action::Discard(0)).unwrap_err(),
+            GameError::DiscardError(DiscardError::NotChooser)
+        );
+        assert_eq!(
+            game.apply(p3, Action::Discard(4)).unwrap_err(),
+            GameError::DiscardError(DiscardError::CardNotFound)
+        );
+
+        let game = game.apply(p3, Action::Discard(0)).unwrap().0;
+        assert!(matches!(game.state(), GameState::Placement(_)));
+        assert_eq!(game.players().player(p1).unwrap().score, 0);
+    }
+
+    #[test]
+    pub fn test_discard_own_skull() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(1)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let skull_index = match game.state() {
+            GameState::Discard(d) => {
+                assert_eq!(d.chooser(), p1);
+                d.selector_cards()
+                    .iter()
+                    .position(|c| *c == Card::Skull)
+                    .unwrap()
+            }
+            s => panic!("unexpected state {:?}", s),
+        };
+
+        let game = game.apply(p1, Action::Discard(skull_index)).unwrap().0;
+        assert_eq!(
+            game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap_err(),
+            GameError::PlacementError(PlacementError::HandError(HandError::CardNotFound))
+        );
+    }
+
+    #[test]
+    pub fn test_elimination() {
+        let (mut game, p1, p2, p3) = new_game();
+        for _ in 0..4 {
+            game = fail_challenge(game, p1, p2, &[p3]);
+        }
+        assert_eq!(
+            game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap_err(),
+            GameError::PlacementError(PlacementError::PlayerEliminated)
+        );
+        assert_eq!(
+            game.apply(p1, Action::Bid(1)).unwrap_err(),
+            GameError::PlacementError(PlacementError::PlayerEliminated)
+        );
+
+        assert_eq!(
+            game.players().player(p1).unwrap().status(),
+            ActiveStatus::Eliminated
+        );
+        assert_eq!(
+            game.players().next_active_player(p3).unwrap().player_id(),
+            p2
+        );
+
+        // The remaining two players can keep playing without the eliminated player.
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::Bid(1)).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        assert!(matches!(game.state(), GameState::Selection(_)));
+    }
+
+    #[test]
+    pub fn test_last_player_standing() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let mut game = Game::new(players).apply(p1, Action::StartGame).unwrap().0;
+        for _ in 0..4 {
+            game = fail_challenge(game, p1, p2, &[]);
+        }
+        match game.state() {
+            GameState::GameOver(g) => assert_eq!(g.winner(), p2),
+            s => panic!("unexpected state {:?}", s),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde_round_trip() {
+        let (game, p1, p2, p3) = new_game();
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+    }
+
+    #[test]
+    pub fn test_events() {
+        let (game, p1, p2, p3) = new_game();
+        let (game, events) = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap();
+        assert_eq!(events, vec![GameEvent::CardPlaced { player_id: p1 }]);
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let (game, events) = game.apply(p1, Action::Bid(2)).unwrap();
+        assert_eq!(
+            events,
+            vec![GameEvent::BidMade {
+                player_id: p1,
+                amount: 2
+            }]
+        );
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let (game, events) = game.apply(p3, Action::Pass).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::Passed { player_id: p3 },
+                GameEvent::ChallengeStarted {
+                    selector: p1,
+                    goal: 2
+                }
+            ]
+        );
+
+        let (game, events) = game.apply(p1, Action::Flip(p1)).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::CardRevealed {
+                    from_player: p1,
+                    card: Card::Skull
+                },
+                GameEvent::ChallengeFailed {
+                    selector: p1,
+                    skull_owner: p1
+                }
+            ]
+        );
+        let (_, events) = game.apply(p1, Action::Discard(0)).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::CardDiscarded { player_id: p1 },
+                GameEvent::RoundStarted {
+                    starting_player: p1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_replay() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let initial = Initialize::new(players.clone());
+        let actions = vec![
+            (p1, Action::StartGame),
+            (p1, Action::PlaceCard(Card::Flower)),
+            (p2, Action::PlaceCard(Card::Skull)),
+            (p3, Action::PlaceCard(Card::Flower)),
+            (p1, Action::Bid(2)),
+            (p2, Action::Pass),
+            (p3, Action::Pass),
+            (p1, Action::Flip(p1)),
+            (p1, Action::Flip(p2)),
+            (p2, Action::Discard(1)),
+        ];
+
+        let game = Game::with_seed(players, 7);
+        let played = actions
+            .iter()
+            .fold(game.clone(), |g, (p, a)| g.apply(*p, *a).unwrap().0);
+        let replayed = Game::replay(initial.clone(), game.seed(), &actions).unwrap();
+        assert_eq!(replayed, played);
+
+        assert_eq!(
+            Game::replay(initial, game.seed(), &actions[1..]).unwrap_err(),
+            ReplayError::ActionFailed {
+                index: 0,
+                error: GameError::InvalidAction
+            }
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_action_for_phase() {
+        let (game, p1, _, _) = new_game();
+        assert_eq!(
+            game.apply(p1, Action::Pass).unwrap_err(),
+            GameError::InvalidAction
+        );
+        assert_eq!(
+            game.apply(p1, Action::Flip(p1)).unwrap_err(),
+            GameError::InvalidAction
+        );
+    }
+}
@@ -1,12 +1,18 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::game_states::placement::Placement;
 use crate::game_states::selection::Selection;
-use crate::types::{Card, Hand, PlayerID, Players};
+use crate::game_states::{has_cards, return_cards};
+use crate::settings::GameSettings;
+use crate::types::{Card, Hand, HandError, PlayerID, Players};
+use crate::views::{Perspective, PhaseView, PlayerView, Table};
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Bid {
     Pass,
     Amount(u8),
@@ -15,7 +21,8 @@ pub enum Bid {
 /// In the bidding phase, players (in order) make bids until:
 /// 1. all players have a defined bid
 /// 2. exactly one player has a `Bid::Amount`
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Bidding {
     players: Players,
     hands: HashMap<PlayerID, Hand>,
@@ -24,17 +31,18 @@ pub struct Bidding {
     /// should never be empty, since we start the bidding phase when someone makes a bid.
     bids: HashMap<PlayerID, Bid>,
     current_player: PlayerID,
+    settings: GameSettings,
 }
 
 impl Bidding {
-    #[must_use]
     pub fn new(
         players: Players,
         hands: HashMap<PlayerID, Hand>,
         cards: HashMap<PlayerID, Vec<Card>>,
         first_bid: (PlayerID, u8),
+        settings: GameSettings,
     ) -> Result<Self, BiddingError> {
-        if first_bid.1 as usize > cards.values().map(|c| c.len()).max().unwrap_or(0) {
+        if first_bid.1 as usize > cards.values().map(|c| c.len()).sum() {
             return Err(BiddingError::BidTooHigh);
         }
         if first_bid.1 == 0 {
@@ -44,53 +52,133 @@ impl Bidding {
         let mut bids = HashMap::new();
         bids.insert(first_bid.0, Bid::Amount(first_bid.1));
 
-        let next_player = players
-            .next_player(first_bid.0)
-            .map(|p| p.player_id)
-            .ok_or(BiddingError::PlayerDoesntExist)?;
-        if players.player_ids().len() < 2 {
-            return Err(BiddingError::InsufficientPlayers);
-        }
-
-        Ok(Self {
-            current_player: next_player,
+        let mut bidding = Self {
+            current_player: first_bid.0,
             players,
             hands,
             cards,
             bids,
-        })
+            settings,
+        };
+        if !bidding.players.player_ids().contains(&first_bid.0) {
+            return Err(BiddingError::PlayerDoesntExist);
+        }
+        if !bidding.has_cards(first_bid.0) {
+            return Err(BiddingError::PlayerEliminated);
+        }
+        if bidding.num_remaining_players() < 2 {
+            return Err(BiddingError::InsufficientPlayers);
+        }
+        bidding.current_player = bidding.next_bidder(first_bid.0);
+
+        Ok(bidding)
+    }
+
+    pub fn current_player(&self) -> PlayerID {
+        self.current_player
+    }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    pub fn bids(&self) -> &'_ HashMap<PlayerID, Bid> {
+        &self.bids
+    }
+
+    /// The current highest bid. Since bidding starts with a bid, this is only `None` if the
+    /// `Bidding` was constructed inconsistently.
+    pub fn highest_bid(&self) -> Option<u8> {
+        self.highest().map(|(_, amount)| amount)
+    }
+
+    /// The player who made the current highest bid.
+    pub fn highest_bidder(&self) -> Option<PlayerID> {
+        self.highest().map(|(player_id, _)| player_id)
+    }
+
+    /// The players who still have to either raise or pass, in turn order starting with the
+    /// current player. The highest bidder isn't included, since they can't outbid themselves.
+    pub fn players_yet_to_act(&self) -> Vec<PlayerID> {
+        let highest_bidder = self.highest_bidder();
+        self.players
+            .iter_from(self.current_player)
+            .filter(|p| {
+                self.has_cards(*p)
+                    && self.bids.get(p).copied() != Some(Bid::Pass)
+                    && Some(*p) != highest_bidder
+            })
+            .collect()
+    }
+
+    /// Compute every player's hand after all placed cards have been returned to their owners,
+    /// e.g. if the round is abandoned. Players with no cards are omitted.
+    pub fn returned_hands(&self) -> Result<HashMap<PlayerID, Hand>, HandError> {
+        return_cards(&self.players, &self.hands, &self.cards, &HashMap::new())
+    }
+
+    pub fn players(&self) -> &'_ Players {
+        &self.players
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        Table::new(
+            perspective,
+            &self.players,
+            &self.hands,
+            &self.cards,
+            &HashMap::new(),
+            PhaseView::Bidding {
+                current_player: self.current_player,
+                bids: self.bids.clone(),
+            },
+        )
     }
 
-    #[must_use]
     pub fn make_bid(&self, player_id: PlayerID, bid: Bid) -> Result<BiddingResult, BiddingError> {
         let existing_bid = self.bids.get(&player_id).copied();
-        let offset = self
-            .players
-            .player_ids()
-            .iter()
-            .position(|p| *p == player_id)
-            .ok_or(BiddingError::PlayerDoesntExist)?;
+        if !self.players.player_ids().contains(&player_id) {
+            return Err(BiddingError::PlayerDoesntExist);
+        }
+        if !self.has_cards(player_id) {
+            return Err(BiddingError::PlayerEliminated);
+        }
+        if !self.settings.speed_bidding
+            && player_id != self.current_player
+            && existing_bid != Some(Bid::Pass)
+        {
+            return Err(BiddingError::NotYourTurn);
+        }
 
-        let min_bid = self
-            .bids
-            .values()
-            .flat_map(|b| match b {
-                Bid::Amount(v) => Some(v),
-                Bid::Pass => None,
-            })
-            .max()
-            .copied()
-            .unwrap_or(0);
+        // The highest bid stands until someone outbids it, or the table would be left with nothing
+        // but passes.
+        if bid == Bid::Pass && self.highest_bidder() == Some(player_id) {
+            return Err(BiddingError::HighestBidderCantPass);
+        }
+
+        let min_bid = self.highest_bid().unwrap_or(0);
         let max_bid = self.cards.values().map(|c| c.len()).sum();
 
         let res = match (existing_bid, bid) {
             (Some(Bid::Pass), Bid::Pass) | (Some(Bid::Pass), Bid::Amount(_)) => {
                 Err(BiddingError::AlreadyPassed)
             }
+            (None, Bid::Amount(n)) | (Some(Bid::Amount(_)), Bid::Amount(n)) if n <= min_bid => {
+                Err(BiddingError::BidTooLow)
+            }
             (None, Bid::Amount(n)) | (Some(Bid::Amount(_)), Bid::Amount(n))
-                if n <= min_bid || n as usize > max_bid =>
+                if n as usize > max_bid =>
             {
-                Err(BiddingError::BidTooLow)
+                Err(BiddingError::BidTooHigh)
             }
             (None, Bid::Pass)
             | (None, Bid::Amount(_))
@@ -99,30 +187,10 @@ impl Bidding {
         };
 
         let new_bidding = res.map(|()| {
-            let mut new_bids = self.bids.clone();
-            new_bids.insert(player_id, bid);
-
-            let next_player = {
-                // Find the next player who has never passed.
-                let mut next = player_id;
-                let num_players = self.players.player_ids().len();
-                for i in 0..num_players {
-                    let p = self.players.player_ids()[(i + offset) % num_players];
-                    if new_bids.get(&p).copied() != Some(Bid::Pass) {
-                        next = p;
-                        break;
-                    }
-                }
-                next
-            };
-
-            Self {
-                players: self.players.clone(),
-                hands: self.hands.clone(),
-                cards: self.cards.clone(),
-                bids: new_bids,
-                current_player: next_player,
-            }
+            let mut new_bidding = self.clone();
+            new_bidding.bids.insert(player_id, bid);
+            new_bidding.current_player = new_bidding.next_bidder(player_id);
+            new_bidding
         })?;
 
         if let Ok(selection) = new_bidding.finish_bidding() {
@@ -132,7 +200,56 @@ impl Bidding {
         }
     }
 
-    #[must_use]
+    /// Take a player out of play mid-round, along with their hand, stack and bid, leaving them as
+    /// an observer. If that
+    /// leaves no bid standing, or the highest bid is more than the cards left on the table, the
+    /// bidding is abandoned and placement resumes with the next player. Otherwise, if everyone
+    /// else has passed, the challenge starts.
+    pub fn remove_player(&self, player_id: PlayerID) -> Result<BiddingResult, BiddingError> {
+        if !self.players.player_ids().contains(&player_id) {
+            return Err(BiddingError::PlayerDoesntExist);
+        }
+        let next_player = self
+            .players
+            .iter_from(player_id)
+            .skip(1)
+            .find(|p| self.has_cards(*p))
+            .ok_or(BiddingError::InsufficientPlayers)?;
+        let next_bidder = self.next_bidder(player_id);
+
+        let mut bidding = self.clone();
+        bidding.players = self
+            .players
+            .make_player_into_observer(player_id)
+            .map_err(|_| BiddingError::PlayerDoesntExist)?;
+        bidding.hands.remove(&player_id);
+        bidding.cards.remove(&player_id);
+        bidding.bids.remove(&player_id);
+        if bidding.num_remaining_players() < 2 {
+            return Err(BiddingError::InsufficientPlayers);
+        }
+
+        let max_bid = bidding.cards.values().map(|c| c.len()).sum::<usize>();
+        match bidding.highest_bid() {
+            Some(amount) if amount as usize <= max_bid => {
+                if bidding.current_player == player_id {
+                    bidding.current_player = next_bidder;
+                }
+                match bidding.finish_bidding() {
+                    Ok(selection) => Ok(BiddingResult::StartSelection(selection)),
+                    Err(_) => Ok(BiddingResult::KeepBidding(bidding)),
+                }
+            }
+            _ => Ok(BiddingResult::ResumePlacement(Placement::resume(
+                next_player,
+                bidding.players,
+                bidding.hands,
+                bidding.cards,
+                bidding.settings,
+            ))),
+        }
+    }
+
     fn finish_bidding(&self) -> Result<Selection, BiddingError> {
         let num_passes = self
             .bids
@@ -148,36 +265,81 @@ impl Bidding {
         });
         let (selector, goal) = iter.next().ok_or(BiddingError::BiddingIncomplete)?;
         // We advance to selection if everyone other than the current selector has passed.
-        if !iter.next().is_none() && num_passes == self.players.player_ids().len() - 1 {
+        if iter.next().is_none() && num_passes == self.num_remaining_players() - 1 {
             let selection = Selection::new(
                 *selector,
                 *goal,
                 self.players.clone(),
                 self.cards.clone(),
                 self.hands.clone(),
+                self.settings,
             )
-            .map_err(|()| BiddingError::BidTooHigh)?;
+            .map_err(|_| BiddingError::BidTooHigh)?;
             Ok(selection)
         } else {
             Err(BiddingError::BiddingIncomplete)
         }
     }
+
+    fn highest(&self) -> Option<(PlayerID, u8)> {
+        self.bids
+            .iter()
+            .flat_map(|(player_id, bid)| match bid {
+                Bid::Amount(amount) => Some((*player_id, *amount)),
+                Bid::Pass => None,
+            })
+            .max_by_key(|(_, amount)| *amount)
+    }
+
+    fn has_cards(&self, player_id: PlayerID) -> bool {
+        has_cards(&self.hands, &self.cards, player_id)
+    }
+
+    /// The number of players who haven't been eliminated.
+    fn num_remaining_players(&self) -> usize {
+        self.players
+            .player_ids()
+            .iter()
+            .filter(|p| self.has_cards(**p))
+            .count()
+    }
+
+    /// Find the next player after `player_id` who has neither passed nor been eliminated. Returns
+    /// `player_id` if there is no such player.
+    fn next_bidder(&self, player_id: PlayerID) -> PlayerID {
+        self.players
+            .iter_from(player_id)
+            .skip(1)
+            .chain(std::iter::once(player_id))
+            .find(|p| self.has_cards(*p) && self.bids.get(p).copied() != Some(Bid::Pass))
+            .unwrap_or(player_id)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BiddingResult {
     KeepBidding(Bidding),
     StartSelection(Selection),
+    /// Only returned by `Bidding::remove_player`, if the bidding had to be abandoned.
+    ResumePlacement(Placement),
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BiddingError {
     #[error("That player doesn't exist")]
     PlayerDoesntExist,
     #[error("Insufficent number of players")]
     InsufficientPlayers,
+    #[error("That player has been eliminated")]
+    PlayerEliminated,
+    #[error("It's not that player's turn to bid")]
+    NotYourTurn,
     #[error("Player has already passed")]
     AlreadyPassed,
+    #[error("The highest bidder can't pass")]
+    HighestBidderCantPass,
     #[error("Bid not higher than existing bid")]
     BidTooLow,
     #[error("Bid higher than acheivable")]
@@ -185,3 +347,145 @@ pub enum BiddingError {
     #[error("All other players must pass")]
     BiddingIncomplete,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Bid, Bidding, BiddingError, BiddingResult};
+    use crate::settings::GameSettings;
+    use crate::types::{Card, Hand, Players};
+
+    #[test]
+    pub fn test_accessors() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let hands = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, Hand::new().remove_card(Card::Flower).unwrap().unwrap()))
+            .collect::<HashMap<_, _>>();
+        let cards = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, vec![Card::Flower]))
+            .collect::<HashMap<_, _>>();
+
+        let new_bidding = |settings| {
+            Bidding::new(
+                players.clone(),
+                hands.clone(),
+                cards.clone(),
+                (p2, 1),
+                settings,
+            )
+            .unwrap()
+        };
+
+        let bidding = new_bidding(GameSettings::default());
+        assert_eq!(bidding.current_player(), p3);
+        assert_eq!(bidding.highest_bid(), Some(1));
+        assert_eq!(bidding.highest_bidder(), Some(p2));
+        assert_eq!(bidding.players_yet_to_act(), vec![p3, p1]);
+        assert_eq!(
+            bidding.make_bid(p1, Bid::Pass),
+            Err(BiddingError::NotYourTurn)
+        );
+        let speed_bidding = new_bidding(GameSettings {
+            speed_bidding: true,
+            ..GameSettings::default()
+        });
+        assert!(speed_bidding.make_bid(p1, Bid::Pass).is_ok());
+        // Even out of turn, the highest bid can't be withdrawn.
+        assert_eq!(
+            speed_bidding.make_bid(p2, Bid::Pass),
+            Err(BiddingError::HighestBidderCantPass)
+        );
+        let speed_bidding = match speed_bidding.make_bid(p1, Bid::Pass).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        match speed_bidding.make_bid(p3, Bid::Pass).unwrap() {
+            BiddingResult::StartSelection(s) => assert_eq!(s.selector(), p2),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let bidding = match bidding.make_bid(p3, Bid::Pass).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        let bidding = match bidding.make_bid(p1, Bid::Amount(2)).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(bidding.current_player(), p2);
+        assert_eq!(bidding.highest_bid(), Some(2));
+        assert_eq!(bidding.highest_bidder(), Some(p1));
+        assert_eq!(bidding.bids()[&p3], Bid::Pass);
+        assert_eq!(bidding.players_yet_to_act(), vec![p2]);
+    }
+
+    #[test]
+    pub fn test_remove_player() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let (players, p4) = players.add_player("p4".to_string()).unwrap();
+        let hands = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, Hand::new().remove_card(Card::Flower).unwrap().unwrap()))
+            .collect::<HashMap<_, _>>();
+        let cards = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, vec![Card::Flower]))
+            .collect::<HashMap<_, _>>();
+        let bidding =
+            Bidding::new(players, hands, cards, (p1, 1), GameSettings::default()).unwrap();
+        let bidding = match bidding.make_bid(p2, Bid::Amount(2)).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(bidding.current_player(), p3);
+
+        // The bid stands, and the departing player's turn passes on.
+        let removed = match bidding.remove_player(p3).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(removed.current_player(), p4);
+        assert_eq!(removed.highest_bidder(), Some(p2));
+        assert_eq!(removed.players_yet_to_act(), vec![p4, p1]);
+
+        // Once everyone else has passed, the challenge starts.
+        let removed = match removed.make_bid(p4, Bid::Pass).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        match removed.remove_player(p1).unwrap() {
+            BiddingResult::StartSelection(s) => assert_eq!(s.selector(), p2),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // Without the highest bidder, the next highest bid stands.
+        match bidding.remove_player(p2).unwrap() {
+            BiddingResult::KeepBidding(b) => assert_eq!(b.highest_bidder(), Some(p1)),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // A bid which can no longer be reached is abandoned.
+        let bidding = match bidding.make_bid(p3, Bid::Amount(4)).unwrap() {
+            BiddingResult::KeepBidding(b) => b,
+            r => panic!("unexpected result {:?}", r),
+        };
+        match bidding.remove_player(p1).unwrap() {
+            BiddingResult::ResumePlacement(p) => {
+                assert_eq!(p.current_player(), p2);
+                assert_eq!(p.total_cards_placed(), 3);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
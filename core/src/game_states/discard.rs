@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game_states::game_over::GameOver;
+use crate::game_states::placement::Placement;
+use crate::settings::GameSettings;
+use crate::types::{Card, Hand, HandError, PlayerID, Players};
+use crate::views::{CardView, Perspective, PhaseView, PlayerView, Table};
+
+/// After a failed challenge, the selector must lose one of their cards. If they flipped their own
+/// skull they choose which card to lose; otherwise, the owner of the skull picks one of the
+/// selector's cards without seeing it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Discard {
+    players: Players,
+    selector: PlayerID,
+    skull_owner: PlayerID,
+    /// Every player's hand, with all placed cards returned to their owners.
+    hands: HashMap<PlayerID, Hand>,
+    /// The selector's cards, shuffled so that the skull owner can't tell them apart.
+    selector_cards: Vec<Card>,
+    settings: GameSettings,
+}
+
+impl Discard {
+    pub fn new(
+        players: Players,
+        hands: HashMap<PlayerID, Hand>,
+        selector: PlayerID,
+        skull_owner: PlayerID,
+        settings: GameSettings,
+        rng: &mut impl Rng,
+    ) -> Result<Self, DiscardError> {
+        let mut selector_cards = hands
+            .get(&selector)
+            .ok_or(DiscardError::PlayerDoesntExist)?
+            .cards()
+            .collect::<Vec<_>>();
+        selector_cards.shuffle(rng);
+        Ok(Self {
+            players,
+            selector,
+            skull_owner,
+            hands,
+            selector_cards,
+            settings,
+        })
+    }
+
+    pub fn selector(&self) -> PlayerID {
+        self.selector
+    }
+
+    pub fn skull_owner(&self) -> PlayerID {
+        self.skull_owner
+    }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    /// The player who picks which card the selector loses. This is the owner of the flipped skull,
+    /// i.e. the selector themselves if they flipped their own skull.
+    pub fn chooser(&self) -> PlayerID {
+        self.skull_owner
+    }
+
+    /// The selector's cards, in the order used by `discard`. Only the selector should see these.
+    pub fn selector_cards(&self) -> &'_ [Card] {
+        &self.selector_cards
+    }
+
+    /// Every player's hand, with all placed cards already returned to their owners.
+    pub fn returned_hands(&self) -> &'_ HashMap<PlayerID, Hand> {
+        &self.hands
+    }
+
+    pub fn players(&self) -> &'_ Players {
+        &self.players
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        let selector_cards = self
+            .selector_cards
+            .iter()
+            .map(|card| {
+                if perspective.can_see(self.selector) {
+                    CardView::Known(*card)
+                } else {
+                    CardView::Hidden
+                }
+            })
+            .collect();
+        Table::new(
+            perspective,
+            &self.players,
+            &self.hands,
+            &HashMap::new(),
+            &HashMap::new(),
+            PhaseView::Discard {
+                selector: self.selector,
+                chooser: self.chooser(),
+                selector_cards,
+            },
+        )
+    }
+
+    /// Discard the selector's card at `index` in `selector_cards`, and start the next round. The
+    /// selector starts the next round unless that was their last card, in which case the owner of
+    /// the skull starts instead (or the next player, if the skull owner has left). Once only one
+    /// player has cards left, they win the game.
+    pub fn discard(
+        &self,
+        player_id: PlayerID,
+        index: usize,
+    ) -> Result<DiscardResult, DiscardError> {
+        if player_id != self.chooser() {
+            return Err(DiscardError::NotChooser);
+        }
+        let card = *self
+            .selector_cards
+            .get(index)
+            .ok_or(DiscardError::CardNotFound)?;
+
+        let mut hands = self.hands.clone();
+        let hand = hands
+            .remove(&self.selector)
+            .ok_or(DiscardError::PlayerDoesntExist)?;
+        if let Some(hand) = hand.remove_card(card)? {
+            hands.insert(self.selector, hand);
+        }
+
+        let starting_player =
+            if hands.contains_key(&self.selector) || !hands.contains_key(&self.skull_owner) {
+                self.selector
+            } else {
+                self.skull_owner
+            };
+        self.next_round(starting_player, hands)
+    }
+
+    /// Take a player out of play before the selector's card is discarded, along with their hand;
+    /// they carry on watching as an observer. If they were going to choose the card, the first of
+    /// `selector_cards` is discarded on their behalf; since the cards are shuffled, that's a random
+    /// pick. If the selector leaves, the discard no longer matters and the next player with cards
+    /// starts the next round. Otherwise the discard is still pending, unless only one player has
+    /// cards left to win.
+    pub fn remove_player(&self, player_id: PlayerID) -> Result<DiscardResult, DiscardError> {
+        let mut discard = self.clone();
+        discard.players = self
+            .players
+            .make_player_into_observer(player_id)
+            .map_err(|_| DiscardError::PlayerDoesntExist)?;
+        discard.hands.remove(&player_id);
+        if player_id == self.selector {
+            let starting_player = self
+                .players
+                .next_active_player(self.selector)
+                .ok_or(DiscardError::InsufficientPlayers)?
+                .player_id();
+            discard.next_round(starting_player, discard.hands.clone())
+        } else if player_id == self.chooser() {
+            discard.discard(player_id, 0)
+        } else if discard.hands.len() < 2 {
+            discard.next_round(self.selector, discard.hands.clone())
+        } else {
+            Ok(DiscardResult::Pending(discard))
+        }
+    }
+
+    fn next_round(
+        &self,
+        starting_player: PlayerID,
+        hands: HashMap<PlayerID, Hand>,
+    ) -> Result<DiscardResult, DiscardError> {
+        Ok(match hands.keys().copied().collect::<Vec<_>>().as_slice() {
+            [] => return Err(DiscardError::InsufficientPlayers),
+            [winner] => {
+                DiscardResult::GameOver(GameOver::new(self.players.clone(), *winner, self.settings))
+            }
+            _ => DiscardResult::NextRound(Placement::new_round(
+                starting_player,
+                self.players.clone(),
+                hands,
+                self.settings,
+            )),
+        })
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum DiscardResult {
+    NextRound(Placement),
+    GameOver(GameOver),
+    /// Only returned by `Discard::remove_player`, if the selector still has to lose a card.
+    Pending(Discard),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiscardError {
+    #[error("That player doesn't exist")]
+    PlayerDoesntExist,
+    #[error("Only the owner of the flipped skull can choose the discarded card")]
+    NotChooser,
+    #[error("That card doesn't exist")]
+    CardNotFound,
+    #[error("Insufficent number of players")]
+    InsufficientPlayers,
+    #[error("Couldn't discard card: {0}")]
+    HandError(#[from] HandError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Discard, DiscardError, DiscardResult};
+    use crate::settings::GameSettings;
+    use crate::types::{Hand, Players};
+
+    #[test]
+    pub fn test_remove_player() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let hands = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, Hand::new()))
+            .collect::<HashMap<_, _>>();
+        let mut rng = StdRng::seed_from_u64(0);
+        let discard =
+            Discard::new(players, hands, p1, p2, GameSettings::default(), &mut rng).unwrap();
+
+        // A bystander leaving doesn't change the penalty.
+        let removed = match discard.remove_player(p3).unwrap() {
+            DiscardResult::Pending(d) => d,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(removed.chooser(), p2);
+        assert_eq!(removed.returned_hands().get(&p3), None);
+
+        // If the chooser leaves, a card is discarded for them.
+        match discard.remove_player(p2).unwrap() {
+            DiscardResult::NextRound(p) => {
+                assert_eq!(p.current_player(), p1);
+                assert_eq!(p.hand(p1).unwrap().cards().count(), 3);
+                assert_eq!(p.players().player_ids(), &[p1, p3]);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // If the selector leaves, the next player starts without any penalty.
+        match discard.remove_player(p1).unwrap() {
+            DiscardResult::NextRound(p) => {
+                assert_eq!(p.current_player(), p2);
+                assert_eq!(p.hand(p2), Some(Hand::new()));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        match removed.remove_player(p1).unwrap() {
+            DiscardResult::GameOver(g) => assert_eq!(g.winner(), p2),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(
+            removed.remove_player(p3),
+            Err(DiscardError::PlayerDoesntExist)
+        );
+    }
+}
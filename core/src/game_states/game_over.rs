@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::game_states::initialize::Initialize;
+use crate::settings::GameSettings;
+use crate::types::{Player, PlayerError, PlayerID, Players};
+use crate::views::{Perspective, PhaseView, PlayerView, Table};
+
+/// The game has ended because a player has won it. No further actions can be taken.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameOver {
+    players: Players,
+    winner: PlayerID,
+    settings: GameSettings,
+}
+
+impl GameOver {
+    #[must_use]
+    pub fn new(players: Players, winner: PlayerID, settings: GameSettings) -> Self {
+        Self {
+            players,
+            winner,
+            settings,
+        }
+    }
+
+    pub fn winner(&self) -> PlayerID {
+        self.winner
+    }
+
+    /// The full set of players (and observers) at the end of the game.
+    pub fn players(&self) -> &'_ Players {
+        &self.players
+    }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    /// The final score of every player in the game, in play order.
+    pub fn scores(&self) -> impl Iterator<Item = (PlayerID, u8)> + '_ {
+        self.players.players().map(|p| (p.player_id, p.score))
+    }
+
+    /// Every player's points, highest first, for games played with `Scoring::Points`. Ties are
+    /// listed in play order.
+    pub fn leaderboard(&self) -> Vec<(PlayerID, u32)> {
+        let mut leaderboard = self
+            .players
+            .players()
+            .map(|p| (p.player_id, p.points))
+            .collect::<Vec<_>>();
+        leaderboard.sort_by_key(|(_, points)| std::cmp::Reverse(*points));
+        leaderboard
+    }
+
+    /// The winning player's information.
+    pub fn winning_player(&self) -> Option<&'_ Player> {
+        self.players.player(self.winner).ok()
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        Table::new(
+            perspective,
+            &self.players,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            PhaseView::GameOver {
+                winner: self.winner,
+            },
+        )
+    }
+
+    /// Set up another game with the same players, with everyone's score reset. If
+    /// `rotate_starting_player` is set, the play order is rotated by one so that a different
+    /// player starts. The settings are kept as they were.
+    pub fn rematch(&self, rotate_starting_player: bool) -> Result<Initialize, PlayerError> {
+        let mut players = self.players.reset_all_scores();
+        if rotate_starting_player {
+            let mut player_ids = players.player_ids().to_vec();
+            if !player_ids.is_empty() {
+                player_ids.rotate_left(1);
+            }
+            players = players.reorder_players(player_ids)?;
+        }
+        Ok(Initialize::new(players).with_validated_settings(self.settings))
+    }
+}
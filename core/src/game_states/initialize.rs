@@ -1,19 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
 
-use crate::types::{Player, PlayerID, Score};
+use crate::game_states::placement::Placement;
+use crate::settings::{GameSettings, SettingsError};
+use crate::types::{PlayerError, PlayerID, Players};
+use crate::views::{Perspective, PhaseView, PlayerView, Table};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+/// In the initialize phase, players gather before the game starts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Initialize {
-    players: Vec<Player>,
+    players: Players,
+    settings: GameSettings,
+    /// The players who are ready to start.
+    ready: HashSet<PlayerID>,
 }
 
 impl Initialize {
-    pub fn new() -> Self {
-        Self { players: vec![] }
+    #[must_use]
+    pub fn new(players: Players) -> Self {
+        Self {
+            players,
+            settings: GameSettings::default(),
+            ready: HashSet::new(),
+        }
+    }
+
+    /// Use the provided rule options instead of the standard rules. Returns an error if they don't
+    /// make a playable game; see `GameSettings::validate`.
+    pub fn with_settings(self, settings: GameSettings) -> Result<Self, SettingsError> {
+        settings.validate()?;
+        Ok(Self { settings, ..self })
+    }
+
+    /// Carry over rule options which were already validated, e.g. for a rematch.
+    #[must_use]
+    pub(crate) fn with_validated_settings(self, settings: GameSettings) -> Self {
+        Self { settings, ..self }
     }
 
-    pub fn players(&self) -> &'_ [Player] {
+    pub fn players(&self) -> &'_ Players {
         &self.players
     }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    /// Whether the player has marked themselves as ready to start.
+    pub fn is_ready(&self, player_id: PlayerID) -> bool {
+        self.ready.contains(&player_id)
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        Table::new(
+            perspective,
+            &self.players,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            PhaseView::Initialize {
+                ready: self
+                    .players
+                    .player_ids()
+                    .iter()
+                    .copied()
+                    .filter(|p| self.is_ready(*p))
+                    .collect(),
+            },
+        )
+    }
+
+    /// Add a player to the game (by name), as with `Players::add_player`, unless the game is
+    /// already at `GameSettings::max_players`. An observer with that name, e.g. someone who
+    /// resigned, rejoins under their existing ID.
+    pub fn add_player(&self, name: String) -> Result<(Self, PlayerID), PlayerError> {
+        let observer = self.players.observers().find(|p| p.name() == name);
+        let (players, player_id) = match observer {
+            // They resigned earlier, and are back.
+            Some(p) => (
+                self.players.make_observer_into_player(p.player_id())?,
+                p.player_id(),
+            ),
+            None => self.players.add_player(name)?,
+        };
+        if players.player_ids().len() > self.settings.max_players {
+            return Err(PlayerError::TooManyPlayers);
+        }
+        Ok((
+            Self {
+                players,
+                ..self.clone()
+            },
+            player_id,
+        ))
+    }
+
+    /// Drop a player out of the game before it starts. They stay on as an observer.
+    pub fn resign(&self, player_id: PlayerID) -> Result<Self, PlayerError> {
+        let mut ready = self.ready.clone();
+        ready.remove(&player_id);
+        Ok(Self {
+            players: self.players.make_player_into_observer(player_id)?,
+            ready,
+            ..self.clone()
+        })
+    }
+
+    /// Mark whether the player is ready to start. If `GameSettings::require_ready` is set, the
+    /// game can only start once every player is ready.
+    pub fn set_ready(&self, player_id: PlayerID, ready: bool) -> Result<Self, PlayerError> {
+        self.players.player(player_id)?;
+        let mut self_ = self.clone();
+        if ready {
+            self_.ready.insert(player_id);
+        } else {
+            self_.ready.remove(&player_id);
+        }
+        Ok(self_)
+    }
+
+    /// Start the game by dealing every player the starting hand from the settings. The first
+    /// player in play order places first; use `Players::reorder_players` beforehand to pick
+    /// someone else.
+    pub fn start_game(&self) -> Result<Placement, PlayerError> {
+        let player_ids = self.players.player_ids();
+        let starting_player = match player_ids {
+            [first, _, ..] if player_ids.len() >= self.settings.min_players => *first,
+            _ => return Err(PlayerError::NotEnoughPlayers),
+        };
+        if player_ids.len() > self.settings.max_players {
+            return Err(PlayerError::TooManyPlayers);
+        }
+        if self.settings.require_ready && !player_ids.iter().all(|p| self.is_ready(*p)) {
+            return Err(PlayerError::PlayersNotReady);
+        }
+        let hands = self
+            .players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, self.settings.starting_hand))
+            .collect();
+        Ok(Placement::new_round(
+            starting_player,
+            self.players.clone(),
+            hands,
+            self.settings,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Initialize;
+    use crate::settings::GameSettings;
+    use crate::types::{PlayerError, PlayerID, Players};
+    use crate::views::PhaseView;
+
+    #[test]
+    pub fn test_max_players() {
+        let settings = GameSettings {
+            max_players: 2,
+            ..GameSettings::default()
+        };
+        let initialize = Initialize::new(Players::new())
+            .with_settings(settings)
+            .unwrap();
+        let (initialize, p1) = initialize.add_player("p1".to_string()).unwrap();
+        let (initialize, _) = initialize.add_player("p2".to_string()).unwrap();
+        assert_eq!(
+            initialize.add_player("p3".to_string()).unwrap_err(),
+            PlayerError::TooManyPlayers
+        );
+        // Rejoining under the same name doesn't take up another seat.
+        assert_eq!(initialize.add_player("p1".to_string()).unwrap().1, p1);
+        let resigned = initialize.resign(p1).unwrap();
+        let (rejoined, same) = resigned.add_player("p1".to_string()).unwrap();
+        assert_eq!(same, p1);
+        assert_eq!(rejoined.players().player_ids().len(), 2);
+        assert_eq!(rejoined.players().observers().count(), 0);
+
+        let (players, _) = initialize.players().add_player("p3".to_string()).unwrap();
+        let initialize = Initialize::new(players).with_settings(settings).unwrap();
+        assert_eq!(
+            initialize.start_game().unwrap_err(),
+            PlayerError::TooManyPlayers
+        );
+    }
+
+    #[test]
+    pub fn test_ready_check() {
+        let settings = GameSettings {
+            require_ready: true,
+            ..GameSettings::default()
+        };
+        let initialize = Initialize::new(Players::new())
+            .with_settings(settings)
+            .unwrap();
+        let (initialize, p1) = initialize.add_player("p1".to_string()).unwrap();
+        let (initialize, p2) = initialize.add_player("p2".to_string()).unwrap();
+        let (initialize, p3) = initialize.add_player("p3".to_string()).unwrap();
+        assert_eq!(
+            initialize.set_ready(PlayerID(42), true).unwrap_err(),
+            PlayerError::PlayerDoesntExist
+        );
+
+        let initialize = initialize.set_ready(p3, true).unwrap();
+        let initialize = initialize.set_ready(p1, true).unwrap();
+        assert!(initialize.is_ready(p1));
+        assert!(!initialize.is_ready(p2));
+        assert_eq!(
+            initialize.view_for(p2).phase,
+            PhaseView::Initialize {
+                ready: vec![p1, p3]
+            }
+        );
+        assert_eq!(
+            initialize.start_game().unwrap_err(),
+            PlayerError::PlayersNotReady
+        );
+        // Without the setting, nobody needs to be ready.
+        assert!(initialize
+            .clone()
+            .with_settings(GameSettings::default())
+            .unwrap()
+            .start_game()
+            .is_ok());
+
+        // Players who drop out don't hold up the game.
+        assert!(initialize.resign(p2).unwrap().start_game().is_ok());
+        let initialize = initialize.set_ready(p2, true).unwrap();
+        assert!(initialize.start_game().is_ok());
+        let initialize = initialize.set_ready(p2, false).unwrap();
+        assert!(!initialize.is_ready(p2));
+    }
 }
@@ -1,14 +1,133 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::settings::GameSettings;
+use crate::types::{Card, Hand, HandError, PlayerID, Players};
+use crate::views::{ObserverView, Perspective, PlayerView, Table, Visibility};
+
 pub mod bidding;
+pub mod discard;
+pub mod game_over;
 pub mod initialize;
 pub mod placement;
 pub mod selection;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum GameState {
     Initialize(initialize::Initialize),
     Placement(placement::Placement),
     Bidding(bidding::Bidding),
     Selection(selection::Selection),
+    Discard(discard::Discard),
+    GameOver(game_over::GameOver),
+}
+
+impl GameState {
+    /// The parts of the current phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    /// The player the game is waiting on, if any.
+    pub fn current_player(&self) -> Option<PlayerID> {
+        match self {
+            GameState::Initialize(_) | GameState::GameOver(_) => None,
+            GameState::Placement(p) => Some(p.current_player()),
+            GameState::Bidding(b) => Some(b.current_player()),
+            GameState::Selection(s) => Some(s.selector()),
+            GameState::Discard(d) => Some(d.chooser()),
+        }
+    }
+
+    /// The rule options the game is being played with.
+    pub fn settings(&self) -> &'_ GameSettings {
+        match self {
+            GameState::Initialize(i) => i.settings(),
+            GameState::Placement(p) => p.settings(),
+            GameState::Bidding(b) => b.settings(),
+            GameState::Selection(s) => s.settings(),
+            GameState::Discard(d) => d.settings(),
+            GameState::GameOver(g) => g.settings(),
+        }
+    }
+
+    /// The game as seen by a spectator with the given visibility.
+    pub fn observer_view(&self, visibility: Visibility) -> ObserverView {
+        ObserverView::new(visibility, self.table(Perspective::Observer(visibility)))
+    }
+
+    /// Everyone in the game, with their scores.
+    pub fn players(&self) -> &'_ Players {
+        match self {
+            GameState::Initialize(i) => i.players(),
+            GameState::Placement(p) => p.players(),
+            GameState::Bidding(b) => b.players(),
+            GameState::Selection(s) => s.players(),
+            GameState::Discard(d) => d.players(),
+            GameState::GameOver(g) => g.players(),
+        }
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        match self {
+            GameState::Initialize(i) => i.players_mut(),
+            GameState::Placement(p) => p.players_mut(),
+            GameState::Bidding(b) => b.players_mut(),
+            GameState::Selection(s) => s.players_mut(),
+            GameState::Discard(d) => d.players_mut(),
+            GameState::GameOver(g) => g.players_mut(),
+        }
+    }
+
+    fn table(&self, perspective: Perspective) -> Table {
+        match self {
+            GameState::Initialize(i) => i.table(perspective),
+            GameState::Placement(p) => p.table(perspective),
+            GameState::Bidding(b) => b.table(perspective),
+            GameState::Selection(s) => s.table(perspective),
+            GameState::Discard(d) => d.table(perspective),
+            GameState::GameOver(g) => g.table(perspective),
+        }
+    }
+}
+
+/// Compute every player's hand after all placed and revealed cards have been returned to their
+/// owners, as happens at the end of a round. Players with no cards are omitted.
+pub(crate) fn return_cards(
+    players: &Players,
+    hands: &HashMap<PlayerID, Hand>,
+    cards: &HashMap<PlayerID, Vec<Card>>,
+    revealed: &HashMap<PlayerID, Vec<Card>>,
+) -> Result<HashMap<PlayerID, Hand>, HandError> {
+    let mut returned = HashMap::new();
+    for player_id in players.player_ids() {
+        let cards = hands
+            .get(player_id)
+            .into_iter()
+            .flat_map(|h| h.cards())
+            .chain(cards.get(player_id).into_iter().flatten().copied())
+            .chain(revealed.get(player_id).into_iter().flatten().copied());
+        if let Some(hand) = Hand::from_cards(cards)? {
+            returned.insert(*player_id, hand);
+        }
+    }
+    Ok(returned)
+}
+
+/// Whether the player has any cards left, either in their hand or placed during this round. Players
+/// without any cards have been eliminated from the game.
+pub(crate) fn has_cards(
+    hands: &HashMap<PlayerID, Hand>,
+    cards: &HashMap<PlayerID, Vec<Card>>,
+    player_id: PlayerID,
+) -> bool {
+    hands.contains_key(&player_id)
+        || cards
+            .get(&player_id)
+            .map(|c| !c.is_empty())
+            .unwrap_or(false)
 }
@@ -1,29 +1,138 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::game_states::bidding::{Bidding, BiddingError};
+use crate::game_states::{has_cards, return_cards};
+use crate::settings::GameSettings;
 use crate::types::{Card, Hand, HandError, PlayerID, Players};
+use crate::views::{Perspective, PhaseView, PlayerView, Table};
 
 /// In the placement phase, each player (in order) must either place a card from their hand into
 /// the `cards`, or make a nonzero bid (which would transition to the `Bidding` phase).
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Placement {
     players: Players,
     hands: HashMap<PlayerID, Hand>,
     cards: HashMap<PlayerID, Vec<Card>>,
     current_player: PlayerID,
+    settings: GameSettings,
 }
 
 impl Placement {
+    /// Start a round with the provided hands. The starting player should be whoever made the last
+    /// challenge, or anyone for the first round. If the starting player has been eliminated, the
+    /// next player who hasn't been eliminated starts instead.
     #[must_use]
+    pub fn new_round(
+        starting_player: PlayerID,
+        players: Players,
+        hands: HashMap<PlayerID, Hand>,
+        settings: GameSettings,
+    ) -> Self {
+        let mut placement = Self {
+            players,
+            hands,
+            cards: HashMap::new(),
+            current_player: starting_player,
+            settings,
+        };
+        if !placement.has_cards(starting_player) {
+            if let Some(next_player) = placement.next_player(starting_player) {
+                placement.current_player = next_player;
+            }
+        }
+        placement
+    }
+
+    /// Resume placing cards after the bidding was abandoned, with everything still on the table.
+    pub(crate) fn resume(
+        current_player: PlayerID,
+        players: Players,
+        hands: HashMap<PlayerID, Hand>,
+        cards: HashMap<PlayerID, Vec<Card>>,
+        settings: GameSettings,
+    ) -> Self {
+        Self {
+            players,
+            hands,
+            cards,
+            current_player,
+            settings,
+        }
+    }
+
+    pub fn current_player(&self) -> PlayerID {
+        self.current_player
+    }
+
+    pub fn players(&self) -> &'_ Players {
+        &self.players
+    }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    /// The number of cards each player has placed this round. Players who haven't placed anything
+    /// are omitted.
+    pub fn stack_sizes(&self) -> HashMap<PlayerID, usize> {
+        self.cards
+            .iter()
+            .map(|(player_id, cards)| (*player_id, cards.len()))
+            .collect()
+    }
+
+    /// The cards remaining in the player's hand, or `None` if they have none left.
+    pub fn hand(&self, player_id: PlayerID) -> Option<Hand> {
+        self.hands.get(&player_id).copied()
+    }
+
+    pub fn total_cards_placed(&self) -> usize {
+        self.cards.values().map(|cards| cards.len()).sum()
+    }
+
+    /// Compute every player's hand after all placed cards have been returned to their owners,
+    /// e.g. if the round is abandoned. Players with no cards are omitted.
+    pub fn returned_hands(&self) -> Result<HashMap<PlayerID, Hand>, HandError> {
+        return_cards(&self.players, &self.hands, &self.cards, &HashMap::new())
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        Table::new(
+            perspective,
+            &self.players,
+            &self.hands,
+            &self.cards,
+            &HashMap::new(),
+            PhaseView::Placement {
+                current_player: self.current_player,
+            },
+        )
+    }
+
     pub fn place_card(&self, player_id: PlayerID, card: Card) -> Result<Placement, PlacementError> {
         let next_player = self
-            .players
             .next_player(player_id)
-            .map(|p| p.player_id)
             .ok_or(PlacementError::PlayerDoesntExist)?;
+        if !self.has_cards(player_id) {
+            return Err(PlacementError::PlayerEliminated);
+        }
+        if player_id != self.current_player {
+            return Err(PlacementError::NotYourTurn);
+        }
 
         let mut new_hands = self.hands.clone();
 
@@ -35,36 +144,233 @@ impl Placement {
         }
 
         let mut new_cards = self.cards.clone();
-        new_cards
-            .entry(player_id)
-            .or_insert_with(Vec::new)
-            .push(card);
+        new_cards.entry(player_id).or_default().push(card);
 
         Ok(Self {
             hands: new_hands,
             cards: new_cards,
             current_player: next_player,
             players: self.players.clone(),
+            settings: self.settings,
         })
     }
 
-    #[must_use]
-    pub fn bid(&self, player_id: PlayerID, amount: u8) -> Result<Bidding, BiddingError> {
-        Bidding::new(
+    /// Take a player out of play mid-round, along with their hand and stack. They become an
+    /// observer. If it was their
+    /// turn, the next player who hasn't been eliminated places next. Fails if fewer than two
+    /// players would be left with cards, since the round can't continue.
+    pub fn remove_player(&self, player_id: PlayerID) -> Result<Placement, PlacementError> {
+        let next_player = self
+            .next_player(player_id)
+            .filter(|p| *p != player_id)
+            .unwrap_or(self.current_player);
+        let mut placement = self.clone();
+        placement.players = self
+            .players
+            .make_player_into_observer(player_id)
+            .map_err(|_| PlacementError::PlayerDoesntExist)?;
+        placement.hands.remove(&player_id);
+        placement.cards.remove(&player_id);
+        if placement.num_remaining_players() < 2 {
+            return Err(PlacementError::InsufficientPlayers);
+        }
+        if placement.current_player == player_id {
+            placement.current_player = next_player;
+        }
+        Ok(placement)
+    }
+
+    /// Open the bidding. The next player who hasn't been eliminated bids next.
+    pub fn bid(&self, player_id: PlayerID, amount: u8) -> Result<Bidding, PlacementError> {
+        if !self.players.player_ids().contains(&player_id) {
+            return Err(PlacementError::PlayerDoesntExist);
+        }
+        if !self.has_cards(player_id) {
+            return Err(PlacementError::PlayerEliminated);
+        }
+        if player_id != self.current_player {
+            return Err(PlacementError::NotYourTurn);
+        }
+        Ok(Bidding::new(
             self.players.clone(),
             self.hands.clone(),
             self.cards.clone(),
             (player_id, amount),
-        )
+            self.settings,
+        )?)
+    }
+
+    fn has_cards(&self, player_id: PlayerID) -> bool {
+        has_cards(&self.hands, &self.cards, player_id)
+    }
+
+    fn num_remaining_players(&self) -> usize {
+        self.players
+            .player_ids()
+            .iter()
+            .filter(|p| self.has_cards(**p))
+            .count()
+    }
+
+    /// Find the next player after `player_id` who hasn't been eliminated.
+    fn next_player(&self, player_id: PlayerID) -> Option<PlayerID> {
+        if !self.players.player_ids().contains(&player_id) {
+            return None;
+        }
+        self.players
+            .iter_from(player_id)
+            .skip(1)
+            .chain(std::iter::once(player_id))
+            .find(|p| self.has_cards(*p))
     }
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PlacementError {
     #[error("That player doesn't exist")]
     PlayerDoesntExist,
     #[error("No cards remaining to place")]
     OutOfCards,
+    #[error("That player has been eliminated")]
+    PlayerEliminated,
+    #[error("It's not that player's turn")]
+    NotYourTurn,
+    #[error("Insufficent number of players")]
+    InsufficientPlayers,
     #[error("Couldn't play card {0}")]
     HandError(#[from] HandError),
+    #[error("Couldn't start bidding: {0}")]
+    BiddingError(#[from] BiddingError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Placement, PlacementError};
+    use crate::game::{Action, Game};
+    use crate::game_states::initialize::Initialize;
+    use crate::game_states::GameState;
+    use crate::settings::GameSettings;
+    use crate::types::{Card, Hand, PlayerID, Players};
+
+    fn new_players() -> (Players, PlayerID, PlayerID, PlayerID) {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        (players, p1, p2, p3)
+    }
+
+    /// Play a round in which `p2` challenges for two cards, flipping `p3`'s `card` second.
+    fn challenge(card: Card) -> (Game, PlayerID, PlayerID, PlayerID) {
+        let (players, p1, p2, p3) = new_players();
+        let game = Game::new(players).apply(p1, Action::StartGame).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(card)).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p3, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Pass).unwrap().0;
+        let game = game.apply(p2, Action::Flip(p2)).unwrap().0;
+        let game = game.apply(p2, Action::Flip(p3)).unwrap().0;
+        (game, p1, p2, p3)
+    }
+
+    fn current_player(game: &Game) -> PlayerID {
+        match game.state() {
+            GameState::Placement(p) => p.current_player,
+            s => panic!("unexpected state {:?}", s),
+        }
+    }
+
+    #[test]
+    pub fn test_challenger_starts_after_success() {
+        let (game, _, p2, _) = challenge(Card::Flower);
+        assert_eq!(current_player(&game), p2);
+    }
+
+    #[test]
+    pub fn test_challenger_starts_after_failure() {
+        let (game, _, p2, p3) = challenge(Card::Skull);
+        let game = game.apply(p3, Action::Discard(0)).unwrap().0;
+        assert_eq!(current_player(&game), p2);
+    }
+
+    #[test]
+    pub fn test_eliminated_player_doesnt_start() {
+        let (players, p1, p2, p3) = new_players();
+        let mut hands = HashMap::new();
+        hands.insert(p2, Hand::new());
+        hands.insert(p3, Hand::new());
+        let placement = Placement::new_round(p1, players, hands, GameSettings::default());
+        assert_eq!(placement.current_player, p2);
+    }
+
+    #[test]
+    pub fn test_remove_player() {
+        let (players, p1, p2, p3) = new_players();
+        let hands = players
+            .player_ids()
+            .iter()
+            .map(|p| (*p, Hand::new()))
+            .collect::<HashMap<_, _>>();
+        let placement = Placement::new_round(p1, players, hands, GameSettings::default());
+        let placement = placement.place_card(p1, Card::Flower).unwrap();
+        let placement = placement.place_card(p2, Card::Skull).unwrap();
+        let placement = placement.place_card(p3, Card::Flower).unwrap();
+
+        let removed = placement.remove_player(p2).unwrap();
+        assert_eq!(removed.current_player(), p1);
+        assert_eq!(removed.players().player_ids(), &[p1, p3]);
+        assert_eq!(removed.hand(p2), None);
+        assert_eq!(removed.stack_sizes().get(&p2), None);
+        assert_eq!(removed.total_cards_placed(), 2);
+
+        let removed = placement.remove_player(p1).unwrap();
+        assert_eq!(removed.current_player(), p2);
+        assert_eq!(
+            removed.remove_player(p1),
+            Err(PlacementError::PlayerDoesntExist)
+        );
+        assert_eq!(
+            removed.remove_player(p2),
+            Err(PlacementError::InsufficientPlayers)
+        );
+    }
+
+    #[test]
+    pub fn test_accessors() {
+        let (players, p1, p2, p3) = new_players();
+        let game = Game::new(players).apply(p1, Action::StartGame).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Skull)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p3, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let placement = match game.state() {
+            GameState::Placement(p) => p.clone(),
+            s => panic!("unexpected state {:?}", s),
+        };
+        assert_eq!(placement.current_player(), p2);
+        assert_eq!(placement.stack_sizes()[&p1], 2);
+        assert_eq!(placement.stack_sizes()[&p2], 1);
+        assert_eq!(placement.total_cards_placed(), 4);
+        let hand = placement.hand(p1).unwrap();
+        assert_eq!((hand.num_flowers(), hand.num_skulls()), (2, 0));
+    }
+
+    #[test]
+    pub fn test_turn_order() {
+        let (players, p1, p2, p3) = new_players();
+        let placement = Initialize::new(players).start_game().unwrap();
+        assert_eq!(
+            placement.place_card(p2, Card::Flower),
+            Err(PlacementError::NotYourTurn)
+        );
+        let placement = placement.place_card(p1, Card::Flower).unwrap();
+        assert_eq!(placement.bid(p3, 1), Err(PlacementError::NotYourTurn));
+        let bidding = placement.bid(p2, 1).unwrap();
+        assert_eq!(bidding.current_player(), p3);
+    }
 }
@@ -1,14 +1,20 @@
 use std::collections::HashMap;
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::types::{Card, Hand, PlayerID, Players};
+use crate::game_states::placement::Placement;
+use crate::game_states::return_cards;
+use crate::settings::GameSettings;
+use crate::types::{Card, Hand, HandError, PlayerID, Players};
+use crate::views::{Perspective, PhaseView, PlayerView, Table};
 
 /// In the `Selection` phase, the `selector` (who has won the bid in the `Bidding` phase) must draw
 /// cards. If they draw `goal` flowers, they win; otherwise, they lose. They are required to draw
 /// their own cards first, after which the player-order is arbitrary.
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Selection {
     players: Players,
     selector: PlayerID,
@@ -16,19 +22,22 @@ pub struct Selection {
     found: u8,
     hands: HashMap<PlayerID, Hand>,
     cards: HashMap<PlayerID, Vec<Card>>,
+    /// The cards which have been flipped so far, keyed by the player who placed them.
+    revealed: HashMap<PlayerID, Vec<Card>>,
+    settings: GameSettings,
 }
 
 impl Selection {
-    #[must_use]
     pub fn new(
         selector: PlayerID,
         goal: u8,
         players: Players,
         cards: HashMap<PlayerID, Vec<Card>>,
         hands: HashMap<PlayerID, Hand>,
-    ) -> Result<Self, ()> {
-        if cards.len() > goal as usize {
-            Err(())
+        settings: GameSettings,
+    ) -> Result<Self, SelectionError> {
+        if cards.values().map(|c| c.len()).sum::<usize>() < goal as usize {
+            Err(SelectionError::GoalTooHigh)
         } else {
             Ok(Self {
                 selector,
@@ -37,34 +46,148 @@ impl Selection {
                 cards,
                 hands,
                 found: 0,
+                revealed: HashMap::new(),
+                settings,
             })
         }
     }
 
-    #[must_use]
+    /// The player who won the bidding and is attempting the challenge.
+    pub fn selector(&self) -> PlayerID {
+        self.selector
+    }
+
+    /// The number of flowers the selector must flip.
+    pub fn goal(&self) -> u8 {
+        self.goal
+    }
+
+    pub fn settings(&self) -> &'_ GameSettings {
+        &self.settings
+    }
+
+    /// The number of flowers the selector has flipped so far.
+    pub fn found(&self) -> u8 {
+        self.found
+    }
+
+    /// The number of face-down cards left in the player's stack.
+    pub fn remaining_cards(&self, player_id: PlayerID) -> usize {
+        self.cards.get(&player_id).map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// The players whose stacks can be flipped next, in play order. The selector must flip all of
+    /// their own cards before anyone else's.
+    pub fn drawable_players(&self) -> Vec<PlayerID> {
+        if self.remaining_cards(self.selector) > 0 {
+            return vec![self.selector];
+        }
+        self.players
+            .player_ids()
+            .iter()
+            .copied()
+            .filter(|p| self.remaining_cards(*p) > 0)
+            .collect()
+    }
+
+    pub fn players(&self) -> &'_ Players {
+        &self.players
+    }
+
+    pub(crate) fn players_mut(&mut self) -> &'_ mut Players {
+        &mut self.players
+    }
+
+    /// The parts of this phase which `viewer` is allowed to see.
+    pub fn view_for(&self, viewer: PlayerID) -> PlayerView {
+        PlayerView::new(viewer, self.table(Perspective::Player(viewer)))
+    }
+
+    pub(crate) fn table(&self, perspective: Perspective) -> Table {
+        Table::new(
+            perspective,
+            &self.players,
+            &self.hands,
+            &self.cards,
+            &self.revealed,
+            PhaseView::Selection {
+                selector: self.selector,
+                goal: self.goal,
+                found: self.found,
+            },
+        )
+    }
+
+    /// Compute every player's hand after all placed and revealed cards have been returned to
+    /// their owners, as happens at the end of a round. Players with no cards are omitted.
+    pub fn returned_hands(&self) -> Result<HashMap<PlayerID, Hand>, HandError> {
+        return_cards(&self.players, &self.hands, &self.cards, &self.revealed)
+    }
+
+    /// Take a player other than the selector out of play mid-challenge, along with their hand and
+    /// stack, so that they're only observing. Any of their flowers which were already flipped still
+    /// count. If the goal can no longer be reached without their stack, the challenge is void:
+    /// nobody scores, everyone gets their cards back and the selector starts a new round. Fails if
+    /// fewer than two players would be left with cards, since the round can't continue.
+    pub fn remove_player(&self, player_id: PlayerID) -> Result<SelectionResult, SelectionError> {
+        if player_id == self.selector {
+            return Err(SelectionError::SelectorRemoved);
+        }
+        let mut selection = self.clone();
+        selection.players = self
+            .players
+            .make_player_into_observer(player_id)
+            .map_err(|_| DrawError::PlayerDoesntExist)?;
+        selection.hands.remove(&player_id);
+        selection.cards.remove(&player_id);
+        selection.revealed.remove(&player_id);
+        let num_remaining_players = selection
+            .players
+            .player_ids()
+            .iter()
+            .filter(|p| {
+                selection.hands.contains_key(p)
+                    || selection.cards.get(p).is_some_and(|c| !c.is_empty())
+                    || selection.revealed.get(p).is_some_and(|c| !c.is_empty())
+            })
+            .count();
+        if num_remaining_players < 2 {
+            return Err(SelectionError::InsufficientPlayers);
+        }
+        let remaining = selection.cards.values().map(|c| c.len()).sum::<usize>();
+        if (selection.found as usize + remaining) < selection.goal as usize {
+            Ok(SelectionResult::Voided(Placement::new_round(
+                selection.selector,
+                selection.players.clone(),
+                selection.returned_hands()?,
+                selection.settings,
+            )))
+        } else {
+            Ok(SelectionResult::More(selection))
+        }
+    }
+
     pub fn pick_card(self, from_player: PlayerID) -> Result<SelectionResult, SelectionError> {
-        if self.selector != from_player
-            && !self
-                .cards
-                .get(&self.selector)
-                .map(|c| c.is_empty())
-                .unwrap_or(false)
-        {
+        if self.selector != from_player && self.remaining_cards(self.selector) > 0 {
             return Err(SelectionError::IncorrectDrawOrder);
         }
         let (card, cards) = self.draw_card(from_player)?;
         Ok(match card {
             Card::Skull => SelectionResult::Failed(from_player),
             Card::Flower if self.found + 1 == self.goal => SelectionResult::Complete(self.selector),
-            Card::Flower => SelectionResult::More(Selection {
-                found: self.found + 1,
-                cards,
-                ..self
-            }),
+            Card::Flower => {
+                let mut revealed = self.revealed;
+                revealed.entry(from_player).or_default().push(card);
+                SelectionResult::More(Selection {
+                    found: self.found + 1,
+                    cards,
+                    revealed,
+                    ..self
+                })
+            }
         })
     }
 
-    #[must_use]
     fn draw_card(
         &self,
         player_id: PlayerID,
@@ -76,7 +199,7 @@ impl Selection {
             match cards_.get_mut(&player_id) {
                 Some(player_cards) => match player_cards.pop() {
                     Some(card) => Ok((card, cards_)),
-                    None => Err(DrawError::PlayerDoesntExist),
+                    None => Err(DrawError::NoCardsLeft),
                 },
                 None => Err(DrawError::PlayerDoesntExist),
             }
@@ -84,25 +207,137 @@ impl Selection {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum SelectionResult {
     Complete(PlayerID),
     More(Selection),
     Failed(PlayerID),
+    /// Only returned by `Selection::remove_player`, if the goal could no longer be reached.
+    Voided(Placement),
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SelectionError {
     #[error("Incorrect draw order")]
     IncorrectDrawOrder,
+    #[error("Not enough cards have been placed to reach the goal")]
+    GoalTooHigh,
+    #[error("The selector can't leave mid-challenge; abandon the round instead")]
+    SelectorRemoved,
+    #[error("Insufficent number of players")]
+    InsufficientPlayers,
+    #[error("Couldn't return cards: {0}")]
+    HandError(#[from] HandError),
     #[error("Couldn't get card: {0}")]
     DrawError(#[from] DrawError),
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum DrawError {
     #[error("That player doesn't exist")]
     PlayerDoesntExist,
     #[error("That player doesn't have any cards left")]
     NoCardsLeft,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{DrawError, Selection, SelectionError, SelectionResult};
+    use crate::settings::GameSettings;
+    use crate::types::{Card, Hand, Players};
+
+    #[test]
+    pub fn test_accessors() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let mut cards = HashMap::new();
+        cards.insert(p1, vec![Card::Flower, Card::Flower]);
+        cards.insert(p2, vec![Card::Flower]);
+        cards.insert(p3, vec![]);
+
+        let selection = Selection::new(
+            p2,
+            3,
+            players,
+            cards,
+            HashMap::new(),
+            GameSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(selection.drawable_players(), vec![p2]);
+        let selection = match selection.pick_card(p2).unwrap() {
+            SelectionResult::More(s) => s,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(selection.found(), 1);
+        assert_eq!(selection.goal(), 3);
+        assert_eq!(selection.remaining_cards(p1), 2);
+        assert_eq!(selection.remaining_cards(p2), 0);
+        assert_eq!(selection.drawable_players(), vec![p1]);
+    }
+
+    #[test]
+    pub fn test_remove_player() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let mut cards = HashMap::new();
+        cards.insert(p1, vec![Card::Flower]);
+        cards.insert(p2, vec![Card::Flower]);
+        cards.insert(p3, vec![Card::Flower, Card::Flower]);
+        let selection = Selection::new(
+            p1,
+            3,
+            players,
+            cards,
+            HashMap::new(),
+            GameSettings::default(),
+        )
+        .unwrap();
+        let selection = match selection.pick_card(p1).unwrap() {
+            SelectionResult::More(s) => s,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(
+            selection.remove_player(p1),
+            Err(SelectionError::SelectorRemoved)
+        );
+
+        let removed = match selection.remove_player(p2).unwrap() {
+            SelectionResult::More(s) => s,
+            r => panic!("unexpected result {:?}", r),
+        };
+        assert_eq!(removed.goal(), 3);
+        assert_eq!(removed.drawable_players(), vec![p3]);
+        assert_eq!(
+            removed.remove_player(p2),
+            Err(SelectionError::DrawError(DrawError::PlayerDoesntExist))
+        );
+
+        // Only the selector would be left with any cards.
+        assert_eq!(
+            removed.remove_player(p3),
+            Err(SelectionError::InsufficientPlayers)
+        );
+
+        // Without `p3`'s stack, the goal can't be reached, so nobody scores.
+        match selection.remove_player(p3).unwrap() {
+            SelectionResult::Voided(p) => {
+                assert_eq!(p.current_player(), p1);
+                assert_eq!(p.total_cards_placed(), 0);
+                assert_eq!(p.hand(p1), Hand::with_counts(1, 0));
+                assert_eq!(p.hand(p2), Hand::with_counts(1, 0));
+                assert_eq!(p.hand(p3), None);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
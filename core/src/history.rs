@@ -0,0 +1,144 @@
+//! Undo and redo support for games, retaining a bounded number of prior states.
+
+use std::collections::VecDeque;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::events::GameEvent;
+use crate::game::{Action, Game, GameError};
+use crate::types::PlayerID;
+
+/// A game along with the states it has passed through. At most `max_states` prior states are
+/// retained for undo; older states are dropped.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct History {
+    /// Prior states, oldest first.
+    past: VecDeque<Game>,
+    current: Game,
+    /// Undone states, most recently undone last.
+    future: Vec<Game>,
+    max_states: usize,
+}
+
+impl History {
+    #[must_use]
+    pub fn new(game: Game, max_states: usize) -> Self {
+        Self {
+            past: VecDeque::new(),
+            current: game,
+            future: vec![],
+            max_states,
+        }
+    }
+
+    pub fn current(&self) -> &'_ Game {
+        &self.current
+    }
+
+    /// The number of states which can currently be undone.
+    pub fn num_undos(&self) -> usize {
+        self.past.len()
+    }
+
+    /// The number of states which can currently be redone.
+    pub fn num_redos(&self) -> usize {
+        self.future.len()
+    }
+
+    /// Apply an action to the current game, recording the prior state and returning the events
+    /// which occurred. Any undone states are discarded.
+    pub fn apply(
+        &mut self,
+        player_id: PlayerID,
+        action: Action,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        let (game, events) = self.current.apply(player_id, action)?;
+        self.push(game);
+        Ok(events)
+    }
+
+    /// Record `game` as the new current state, e.g. after `Game::rematch` or `Game::join`. Any
+    /// undone states are discarded.
+    pub fn push(&mut self, game: Game) {
+        let prior = std::mem::replace(&mut self.current, game);
+        self.past.push_back(prior);
+        while self.past.len() > self.max_states {
+            self.past.pop_front();
+        }
+        self.future.clear();
+    }
+
+    /// Roll back the last `n` states.
+    pub fn undo(&mut self, n: usize) -> Result<(), HistoryError> {
+        if n > self.past.len() {
+            return Err(HistoryError::NothingToUndo);
+        }
+        for _ in 0..n {
+            if let Some(prior) = self.past.pop_back() {
+                let undone = std::mem::replace(&mut self.current, prior);
+                self.future.push(undone);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reapply the last `n` undone states.
+    pub fn redo(&mut self, n: usize) -> Result<(), HistoryError> {
+        if n > self.future.len() {
+            return Err(HistoryError::NothingToRedo);
+        }
+        for _ in 0..n {
+            if let Some(next) = self.future.pop() {
+                let prior = std::mem::replace(&mut self.current, next);
+                self.past.push_back(prior);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HistoryError {
+    #[error("Not enough history to undo")]
+    NothingToUndo,
+    #[error("Not enough undone states to redo")]
+    NothingToRedo,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, HistoryError};
+    use crate::game::{Action, Game};
+    use crate::types::{Card, Players};
+
+    #[test]
+    pub fn test_undo_redo() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let mut history = History::new(Game::new(players), 2);
+
+        history.apply(p1, Action::StartGame).unwrap();
+        let started = history.current().clone();
+        history.apply(p1, Action::PlaceCard(Card::Flower)).unwrap();
+        let placed = history.current().clone();
+        history.apply(p2, Action::PlaceCard(Card::Skull)).unwrap();
+        assert_eq!(history.num_undos(), 2);
+
+        assert_eq!(history.undo(3), Err(HistoryError::NothingToUndo));
+        history.undo(2).unwrap();
+        assert_eq!(history.current(), &started);
+        assert_eq!(history.num_redos(), 2);
+
+        history.redo(1).unwrap();
+        assert_eq!(history.current(), &placed);
+        assert_eq!(history.redo(2), Err(HistoryError::NothingToRedo));
+
+        history.apply(p2, Action::PlaceCard(Card::Flower)).unwrap();
+        assert_eq!(history.num_redos(), 0);
+        assert_eq!(history.num_undos(), 2);
+    }
+}
@@ -1,2 +1,20 @@
+//! The rules engine for Skull.
+//!
+//! Most users should drive a game through `Game::apply`, and import the types they need from
+//! `prelude`. The error enums, the phase result enums, `Action` and `GameState` are
+//! `#[non_exhaustive]`, since new rules and phases may add variants.
+//!
+//! The `serde` feature (enabled by default) derives `Serialize` and `Deserialize` for the game
+//! state. Disable default features to depend on the rules alone. Deserialized state is trusted
+//! as-is: the engine has no untrusted parsing entry points of its own, so size limits and
+//! validation of payloads from clients belong to the server or client that embeds it.
+
+pub mod events;
+pub mod game;
 pub mod game_states;
+pub mod history;
+pub mod prelude;
+pub mod settings;
+pub mod spec;
 pub mod types;
+pub mod views;
@@ -0,0 +1,21 @@
+//! The stable public API of the engine. Downstream crates should prefer importing from here, e.g.
+//! `use skull_core::prelude::*;`, over reaching into individual modules, whose layout may change
+//! between releases.
+
+pub use crate::events::GameEvent;
+pub use crate::game::{Action, Game, GameError, ReplayError};
+pub use crate::game_states::bidding::{Bid, Bidding, BiddingError, BiddingResult};
+pub use crate::game_states::discard::{Discard, DiscardError, DiscardResult};
+pub use crate::game_states::game_over::GameOver;
+pub use crate::game_states::initialize::Initialize;
+pub use crate::game_states::placement::{Placement, PlacementError};
+pub use crate::game_states::selection::{DrawError, Selection, SelectionError, SelectionResult};
+pub use crate::game_states::GameState;
+pub use crate::history::{History, HistoryError};
+pub use crate::settings::{GameSettings, Scoring, SettingsError, TimeoutPolicy};
+pub use crate::spec::{ActionKind, Phase};
+pub use crate::types::{
+    ActiveStatus, Card, Hand, HandError, Player, PlayerError, PlayerID, Players, Presence, Profile,
+    Timestamp,
+};
+pub use crate::views::{CardView, ObserverView, PhaseView, PlayerView, SeatView, Visibility};
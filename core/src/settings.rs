@@ -0,0 +1,169 @@
+//! Rule options which a room can customize. The defaults are the standard rules of Skull.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::types::Hand;
+
+/// Options which are fixed when the game is set up and carried through every phase.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameSettings {
+    /// The number of players needed to start the game. Games always need at least two players,
+    /// regardless of this setting.
+    pub min_players: usize,
+    /// The most players who can take part in the game. The standard rules allow up to six.
+    pub max_players: usize,
+    /// The hand every player is dealt at the start of the game, e.g. two skulls or five flowers
+    /// for variants.
+    pub starting_hand: Hand,
+    /// How long each player has to act, in milliseconds. Deadlines are only tracked for actions
+    /// applied with `Game::apply_at`.
+    pub turn_time_limit: Option<u64>,
+    /// How long a disconnected player has to reconnect, in milliseconds, before their turn runs
+    /// out early. Their turn still runs out at the regular deadline.
+    pub disconnect_time_limit: Option<u64>,
+    /// What happens when a player runs out of time. See `Game::apply_timeout`.
+    pub timeout_policy: TimeoutPolicy,
+    /// Whether every player must be ready (see `Initialize::set_ready`) before the game can start.
+    pub require_ready: bool,
+    /// Under "speed bidding" house rules, players may bid or pass out of turn.
+    pub speed_bidding: bool,
+    pub scoring: Scoring,
+}
+
+impl GameSettings {
+    /// Check that the options make a playable game, e.g. that `min_players` isn't more than
+    /// `max_players`.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.max_players < 2 {
+            return Err(SettingsError::MaxPlayersTooLow);
+        }
+        if self.min_players > self.max_players {
+            return Err(SettingsError::MinPlayersAboveMax);
+        }
+        // Without a flower, nobody could ever win a challenge.
+        if self.starting_hand.num_flowers() == 0 {
+            return Err(SettingsError::NoStartingFlowers);
+        }
+        match self.scoring {
+            Scoring::Challenges { wins_required: 0 } => Err(SettingsError::NoWinsRequired),
+            Scoring::Points { rounds: 0, .. } => Err(SettingsError::NoRounds),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            min_players: 2,
+            max_players: 6,
+            starting_hand: Hand::new(),
+            turn_time_limit: None,
+            disconnect_time_limit: None,
+            timeout_policy: TimeoutPolicy::AutoAction,
+            require_ready: false,
+            speed_bidding: false,
+            scoring: Scoring::Challenges { wins_required: 2 },
+        }
+    }
+}
+
+/// What happens when a player runs out of time on their turn.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum TimeoutPolicy {
+    /// Take the most cautious action on the player's behalf: place a random card (or open the
+    /// bidding at one if their hand is empty), pass, flip their own stack before picking a random
+    /// one, or discard a random card.
+    AutoAction,
+    /// The player resigns, as with `Action::Resign`.
+    Forfeit,
+}
+
+/// How the winner of the game is decided. In either mode, a player who is the last one left with
+/// cards wins immediately.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Scoring {
+    /// The first player to win `wins_required` challenges wins the game. The standard rules
+    /// require two.
+    Challenges { wins_required: u8 },
+    /// Every successful challenge is worth `points_per_challenge` points, and the game ends after
+    /// `rounds` rounds. The player with the most points wins, with ties going to whoever is first
+    /// in play order.
+    Points {
+        points_per_challenge: u32,
+        rounds: u32,
+    },
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SettingsError {
+    #[error("Games need room for at least two players")]
+    MaxPlayersTooLow,
+    #[error("The minimum number of players is more than the maximum")]
+    MinPlayersAboveMax,
+    #[error("The starting hand must include at least one flower")]
+    NoStartingFlowers,
+    #[error("Winning must take at least one challenge")]
+    NoWinsRequired,
+    #[error("Points games must last at least one round")]
+    NoRounds,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameSettings, Scoring, SettingsError};
+    use crate::types::Hand;
+
+    #[test]
+    pub fn test_validate() {
+        assert_eq!(GameSettings::default().validate(), Ok(()));
+        let invalid = |settings: GameSettings| settings.validate().unwrap_err();
+        assert_eq!(
+            invalid(GameSettings {
+                max_players: 1,
+                min_players: 1,
+                ..GameSettings::default()
+            }),
+            SettingsError::MaxPlayersTooLow
+        );
+        assert_eq!(
+            invalid(GameSettings {
+                min_players: 4,
+                max_players: 3,
+                ..GameSettings::default()
+            }),
+            SettingsError::MinPlayersAboveMax
+        );
+        assert_eq!(
+            invalid(GameSettings {
+                starting_hand: Hand::with_counts(0, 2).unwrap(),
+                ..GameSettings::default()
+            }),
+            SettingsError::NoStartingFlowers
+        );
+        assert_eq!(
+            invalid(GameSettings {
+                scoring: Scoring::Challenges { wins_required: 0 },
+                ..GameSettings::default()
+            }),
+            SettingsError::NoWinsRequired
+        );
+        assert_eq!(
+            invalid(GameSettings {
+                scoring: Scoring::Points {
+                    points_per_challenge: 1,
+                    rounds: 0
+                },
+                ..GameSettings::default()
+            }),
+            SettingsError::NoRounds
+        );
+    }
+}
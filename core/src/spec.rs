@@ -0,0 +1,211 @@
+//! The legal phase transitions, as data. `Game::apply` checks every transition it makes against
+//! `TRANSITIONS` in debug builds, and `to_dot` renders the table as a Graphviz diagram for the
+//! documentation.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::game::Action;
+use crate::game_states::GameState;
+
+/// The phases of the game, without their state.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Phase {
+    Initialize,
+    Placement,
+    Bidding,
+    Selection,
+    Discard,
+    GameOver,
+}
+
+impl Phase {
+    pub fn of(state: &GameState) -> Self {
+        match state {
+            GameState::Initialize(_) => Phase::Initialize,
+            GameState::Placement(_) => Phase::Placement,
+            GameState::Bidding(_) => Phase::Bidding,
+            GameState::Selection(_) => Phase::Selection,
+            GameState::Discard(_) => Phase::Discard,
+            GameState::GameOver(_) => Phase::GameOver,
+        }
+    }
+}
+
+/// The kinds of `Action`, without their arguments.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ActionKind {
+    PlaceCard,
+    Bid,
+    Pass,
+    Flip,
+    Discard,
+    StartGame,
+    Ready,
+    Resign,
+    Concede,
+}
+
+impl ActionKind {
+    pub fn of(action: &Action) -> Self {
+        match action {
+            Action::PlaceCard(_) => ActionKind::PlaceCard,
+            Action::Bid(_) => ActionKind::Bid,
+            Action::Pass => ActionKind::Pass,
+            Action::Flip(_) => ActionKind::Flip,
+            Action::Discard(_) => ActionKind::Discard,
+            Action::StartGame => ActionKind::StartGame,
+            Action::Ready(_) => ActionKind::Ready,
+            Action::Resign => ActionKind::Resign,
+            Action::Concede => ActionKind::Concede,
+        }
+    }
+}
+
+/// An action which is valid in the `from` phase, and the phases it can lead to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Transition {
+    pub from: Phase,
+    pub action: ActionKind,
+    pub to: &'static [Phase],
+}
+
+const fn t(from: Phase, action: ActionKind, to: &'static [Phase]) -> Transition {
+    Transition { from, action, to }
+}
+
+/// Where a resignation (or concession by anyone but the selector) can lead mid-round: the round
+/// carries on without the player, possibly into a later phase or a new round, or the game is over
+/// if they leave a single player standing.
+const RESIGNED: &[Phase] = &[
+    Phase::Placement,
+    Phase::Bidding,
+    Phase::Selection,
+    Phase::Discard,
+    Phase::GameOver,
+];
+
+/// Every action the engine accepts, by phase. Anything not listed is rejected with
+/// `GameError::InvalidAction` or a phase-specific error.
+pub const TRANSITIONS: &[Transition] = &[
+    t(
+        Phase::Initialize,
+        ActionKind::StartGame,
+        &[Phase::Placement],
+    ),
+    t(Phase::Initialize, ActionKind::Ready, &[Phase::Initialize]),
+    t(Phase::Initialize, ActionKind::Resign, &[Phase::Initialize]),
+    t(Phase::Initialize, ActionKind::Concede, &[Phase::Initialize]),
+    t(Phase::Placement, ActionKind::PlaceCard, &[Phase::Placement]),
+    t(Phase::Placement, ActionKind::Bid, &[Phase::Bidding]),
+    t(Phase::Placement, ActionKind::Resign, RESIGNED),
+    t(Phase::Placement, ActionKind::Concede, RESIGNED),
+    t(
+        Phase::Bidding,
+        ActionKind::Bid,
+        &[Phase::Bidding, Phase::Selection],
+    ),
+    t(
+        Phase::Bidding,
+        ActionKind::Pass,
+        &[Phase::Bidding, Phase::Selection],
+    ),
+    t(Phase::Bidding, ActionKind::Resign, RESIGNED),
+    t(Phase::Bidding, ActionKind::Concede, RESIGNED),
+    t(
+        Phase::Selection,
+        ActionKind::Flip,
+        &[
+            Phase::Selection,
+            Phase::Placement,
+            Phase::Discard,
+            Phase::GameOver,
+        ],
+    ),
+    t(Phase::Selection, ActionKind::Resign, RESIGNED),
+    // The selector conceding leads to the discard, which `RESIGNED` already covers.
+    t(Phase::Selection, ActionKind::Concede, RESIGNED),
+    t(
+        Phase::Discard,
+        ActionKind::Discard,
+        &[Phase::Placement, Phase::GameOver],
+    ),
+    t(Phase::Discard, ActionKind::Resign, RESIGNED),
+    t(Phase::Discard, ActionKind::Concede, RESIGNED),
+];
+
+/// Whether `TRANSITIONS` allows `action` to take the game from `from` to `to`.
+pub fn is_allowed(from: Phase, action: ActionKind, to: Phase) -> bool {
+    TRANSITIONS
+        .iter()
+        .any(|t| t.from == from && t.action == action && t.to.contains(&to))
+}
+
+/// Render `TRANSITIONS` as a Graphviz digraph, with one edge per action and destination.
+pub fn to_dot() -> String {
+    let mut dot = String::from("digraph skull {\n");
+    for t in TRANSITIONS {
+        for to in t.to {
+            dot.push_str(&format!(
+                "    {:?} -> {:?} [label=\"{:?}\"];\n",
+                t.from, to, t.action
+            ));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_allowed, to_dot, ActionKind, Phase, TRANSITIONS};
+
+    #[test]
+    pub fn test_transitions() {
+        assert!(is_allowed(
+            Phase::Initialize,
+            ActionKind::StartGame,
+            Phase::Placement
+        ));
+        assert!(is_allowed(
+            Phase::Selection,
+            ActionKind::Flip,
+            Phase::GameOver
+        ));
+        assert!(!is_allowed(
+            Phase::Placement,
+            ActionKind::Flip,
+            Phase::Selection
+        ));
+        assert!(!is_allowed(
+            Phase::GameOver,
+            ActionKind::StartGame,
+            Phase::Placement
+        ));
+        // Every action is listed at most once per phase.
+        for (i, a) in TRANSITIONS.iter().enumerate() {
+            assert!(TRANSITIONS[i + 1..]
+                .iter()
+                .all(|b| (a.from, a.action) != (b.from, b.action)));
+        }
+
+        // Anyone but the selector conceding resigns, so it can lead anywhere a resignation can.
+        for resign in TRANSITIONS
+            .iter()
+            .filter(|t| t.action == ActionKind::Resign)
+        {
+            assert!(resign
+                .to
+                .iter()
+                .all(|to| is_allowed(resign.from, ActionKind::Concede, *to)));
+        }
+
+        let dot = to_dot();
+        assert!(dot.starts_with("digraph skull {\n"));
+        assert!(dot.contains("    Placement -> Bidding [label=\"Bid\"];\n"));
+    }
+}
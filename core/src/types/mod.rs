@@ -1,135 +1,230 @@
 //! Generic types used throughout the core codebase.
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 mod players;
 
-pub use players::Players;
+pub use players::{PlayerError, Players};
 
 /// A unique identifier for a player.
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
-#[serde(transparent)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct PlayerID(pub u32);
 
+/// A point in time, in milliseconds since an epoch of the caller's choosing. The engine never reads
+/// the clock itself, so that games stay deterministic.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Timestamp(pub u64);
+
+/// Whether a player is currently connected to the game.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Presence {
+    Connected,
+    Disconnected { since: Timestamp },
+}
+
+/// Whether a player is still in the running. Observers don't have a status, since they aren't
+/// playing at all.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ActiveStatus {
+    Active,
+    /// The player lost all of their cards or resigned, and takes no more turns this game.
+    Eliminated,
+}
+
+/// Optional cosmetic details, so that clients can show a player the same way everywhere.
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Profile {
+    /// An avatar ID or image URL, of at most 512 bytes.
+    pub avatar: Option<String>,
+    /// A display color such as `#ff8800`, of at most 32 bytes.
+    pub color: Option<String>,
+    /// A short tagline, of at most 128 bytes.
+    pub tagline: Option<String>,
+}
+
+impl Profile {
+    /// Whether every field is within its length limit.
+    pub(crate) fn is_valid(&self) -> bool {
+        let within =
+            |field: &Option<String>, max| field.as_ref().map(|s| s.len() <= max).unwrap_or(true);
+        within(&self.avatar, 512) && within(&self.color, 32) && within(&self.tagline, 128)
+    }
+}
+
 /// Information tracked about a player throughout the game.
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Player {
     pub(crate) player_id: PlayerID,
     pub(crate) name: String,
-    pub(crate) score: Score,
+    /// The number of challenges won in the `Scoring::Challenges` mode.
+    pub(crate) score: u8,
+    /// Points earned in the `Scoring::Points` mode.
+    pub(crate) points: u32,
+    pub(crate) presence: Presence,
+    pub(crate) status: ActiveStatus,
+    pub(crate) profile: Profile,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
-pub enum Score {
-    Zero,
-    WonOne,
-    WonGame,
+impl Player {
+    pub fn player_id(&self) -> PlayerID {
+        self.player_id
+    }
+
+    pub fn name(&self) -> &'_ str {
+        &self.name
+    }
+
+    pub fn score(&self) -> u8 {
+        self.score
+    }
+
+    pub fn points(&self) -> u32 {
+        self.points
+    }
+
+    pub fn presence(&self) -> Presence {
+        self.presence
+    }
+
+    pub fn status(&self) -> ActiveStatus {
+        self.status
+    }
+
+    pub fn profile(&self) -> &'_ Profile {
+        &self.profile
+    }
 }
 
 /// A card in the game. Note: Cards don't carry whether they are visible or not.
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Card {
     Flower,
     Skull,
 }
 
-/// The cards that remain in a player's hand. A player can have at most one skull card, and should
-/// have at most four total cards. Their hand should never be empty (i.e. `Option::<Hand>::None`
-/// should be used instead).
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+/// The cards that remain in a player's hand. By default a player starts with one skull and three
+/// flowers, but variants can deal other compositions via `GameSettings`. Their hand should never be
+/// empty (i.e. `Option::<Hand>::None` should be used instead).
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Hand {
-    num_cards: u8,
-    has_skull: bool,
+    num_flowers: u8,
+    num_skulls: u8,
 }
 
 impl Hand {
+    /// The standard starting hand of one skull and three flowers.
     #[must_use]
     pub fn new() -> Self {
         Self {
-            num_cards: 4,
-            has_skull: true,
+            num_flowers: 3,
+            num_skulls: 1,
+        }
+    }
+
+    /// A hand with the provided number of each card, or `None` if that would be empty.
+    pub fn with_counts(num_flowers: u8, num_skulls: u8) -> Option<Hand> {
+        if num_flowers == 0 && num_skulls == 0 {
+            None
+        } else {
+            Some(Self {
+                num_flowers,
+                num_skulls,
+            })
         }
     }
 
     pub fn num_flowers(self) -> usize {
-        self.num_cards as usize - self.num_skulls()
+        self.num_flowers as usize
     }
 
     pub fn num_skulls(self) -> usize {
-        if self.has_skull {
-            1
-        } else {
-            0
-        }
+        self.num_skulls as usize
     }
 
     pub fn num_cards(self) -> usize {
-        self.num_cards as usize
+        self.num_flowers() + self.num_skulls()
     }
 
+    // `repeat_n` would need Rust 1.82.
+    #[allow(clippy::manual_repeat_n)]
     pub fn cards(self) -> impl Iterator<Item = Card> {
         std::iter::repeat(Card::Skull)
             .take(self.num_skulls())
             .chain(std::iter::repeat(Card::Flower).take(self.num_flowers()))
     }
 
-    #[must_use]
     pub fn remove_card(self, card: Card) -> Result<Option<Hand>, HandError> {
-        let has_card = match card {
-            Card::Skull if self.has_skull => true,
-            Card::Flower if self.num_flowers() > 0 => true,
-            _ => false,
+        let (num_flowers, num_skulls) = match card {
+            Card::Flower if self.num_flowers > 0 => (self.num_flowers - 1, self.num_skulls),
+            Card::Skull if self.num_skulls > 0 => (self.num_flowers, self.num_skulls - 1),
+            _ => return Err(HandError::CardNotFound),
         };
-        if !has_card {
-            Err(HandError::CardNotFound)
-        } else if self.num_cards > 1 {
-            match card {
-                Card::Skull => Ok(Some(Self {
-                    num_cards: self.num_cards - 1,
-                    has_skull: false,
-                })),
-                Card::Flower => Ok(Some(Self {
-                    num_cards: self.num_cards - 1,
-                    has_skull: self.has_skull,
-                })),
-            }
-        } else {
-            Ok(None)
-        }
+        Ok(Self::with_counts(num_flowers, num_skulls))
     }
 
-    #[must_use]
     pub fn add_card(self, card: Card) -> Result<Hand, HandError> {
-        if self.num_cards >= 4 || (self.num_flowers() >= 3 && card == Card::Flower) {
-            return Err(HandError::TooManyCards);
-        }
-        match card {
-            Card::Skull if self.has_skull => Err(HandError::TooManyCards),
-            Card::Skull => Ok(Self {
-                num_cards: self.num_cards + 1,
-                has_skull: true,
-            }),
-            Card::Flower => Ok(Self {
-                num_cards: self.num_cards + 1,
-                has_skull: self.has_skull,
+        let (num_flowers, num_skulls) = match card {
+            Card::Flower => (self.num_flowers.checked_add(1), Some(self.num_skulls)),
+            Card::Skull => (Some(self.num_flowers), self.num_skulls.checked_add(1)),
+        };
+        match (num_flowers, num_skulls) {
+            (Some(num_flowers), Some(num_skulls)) => Ok(Self {
+                num_flowers,
+                num_skulls,
             }),
+            _ => Err(HandError::TooManyCards),
         }
     }
 
     #[must_use]
     pub fn from_single_card(card: Card) -> Hand {
-        Self {
-            num_cards: 1,
-            has_skull: match card {
-                Card::Skull => true,
-                Card::Flower => false,
+        match card {
+            Card::Flower => Self {
+                num_flowers: 1,
+                num_skulls: 0,
+            },
+            Card::Skull => Self {
+                num_flowers: 0,
+                num_skulls: 1,
             },
         }
     }
+
+    /// Build a hand out of the provided cards, e.g. when returning placed cards to their owner.
+    /// Returns `None` if there are no cards.
+    pub fn from_cards(cards: impl IntoIterator<Item = Card>) -> Result<Option<Hand>, HandError> {
+        let mut hand: Option<Hand> = None;
+        for card in cards {
+            hand = Some(match hand {
+                Some(h) => h.add_card(card)?,
+                None => Self::from_single_card(card),
+            });
+        }
+        Ok(hand)
+    }
+}
+
+impl Default for Hand {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum HandError {
     #[error("Too many cards in the hand")]
     TooManyCards,
@@ -176,11 +271,6 @@ mod tests {
     pub fn test_add_cards_to_hand() {
         let h = Hand::from_single_card(Card::Skull);
 
-        assert_eq!(
-            h.add_card(Card::Skull).unwrap_err(),
-            HandError::TooManyCards
-        );
-
         assert_eq!(h.num_cards(), 1);
         assert_eq!(h.num_flowers(), 0);
         assert_eq!(h.num_skulls(), 1);
@@ -196,13 +286,19 @@ mod tests {
         assert_eq!(new_h2.num_skulls(), 1);
 
         let new_h3 = new_h2.add_card(Card::Flower).unwrap();
-        assert_eq!(new_h3.num_cards(), 4);
-        assert_eq!(new_h3.num_flowers(), 3);
-        assert_eq!(new_h3.num_skulls(), 1);
+        assert_eq!(new_h3, Hand::new());
+
+        // Variants may deal more than one skull.
+        let new_h4 = new_h3.add_card(Card::Skull).unwrap();
+        assert_eq!(new_h4, Hand::with_counts(3, 2).unwrap());
 
         assert_eq!(
-            new_h3.add_card(Card::Flower).unwrap_err(),
+            Hand::with_counts(u8::MAX, 0)
+                .unwrap()
+                .add_card(Card::Flower)
+                .unwrap_err(),
             HandError::TooManyCards
         );
+        assert_eq!(Hand::with_counts(0, 0), None);
     }
 }
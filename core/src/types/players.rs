@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 
+use rand::seq::SliceRandom;
+use rand::Rng;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::{Player, PlayerID, Score};
+use super::{ActiveStatus, Player, PlayerID, Presence, Profile};
 
 /// The set of players playing the game.
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Players {
     /// The ordered list of player IDs, used to determine the next player.
     player_ids: Vec<PlayerID>,
@@ -38,7 +42,22 @@ impl Players {
     pub fn players(&self) -> impl Iterator<Item = &'_ Player> {
         self.player_ids
             .iter()
-            .flat_map(move |id| self.players.get(&id))
+            .flat_map(move |id| self.players.get(id))
+    }
+
+    /// Get the IDs of the players in play order, starting with `player_id` and wrapping around, or
+    /// starting with the first player if `player_id` isn't playing. Skip the first item to start
+    /// after `player_id` instead.
+    pub fn iter_from(&self, player_id: PlayerID) -> impl Iterator<Item = PlayerID> + '_ {
+        let offset = self
+            .player_ids
+            .iter()
+            .position(|p| *p == player_id)
+            .unwrap_or(0);
+        self.player_ids[offset..]
+            .iter()
+            .chain(&self.player_ids[..offset])
+            .copied()
     }
 
     /// Get all of the players which are not currently in the game, in arbitrary order.
@@ -50,10 +69,23 @@ impl Players {
     /// player is not found or the next player does not exist.
     pub fn next_player(&self, player_id: PlayerID) -> Option<&'_ Player> {
         let index = self.player_ids.iter().position(|p| *p == player_id)?;
-        let next_player = self.player_ids[(index + 1) % self.players.len()];
+        let next_player = self.player_ids[(index + 1) % self.player_ids.len()];
         self.players.get(&next_player)
     }
 
+    /// Get the next player after the provided `player_id` who hasn't been eliminated, wrapping
+    /// around. Returns `None` if the player is not found or nobody else is active. `Game` updates
+    /// everyone's status after each action, so this is only up to date between actions.
+    pub fn next_active_player(&self, player_id: PlayerID) -> Option<&'_ Player> {
+        if !self.player_ids.contains(&player_id) {
+            return None;
+        }
+        self.iter_from(player_id)
+            .skip(1)
+            .flat_map(move |id| self.players.get(&id))
+            .find(|p| p.status == ActiveStatus::Active)
+    }
+
     /// Get the (playing) player by PlayerID. Returns `PlayerDoesntExist` if not
     /// found, including if the player is currently observing.
     pub fn player(&self, id: PlayerID) -> Result<&'_ Player, PlayerError> {
@@ -77,7 +109,11 @@ impl Players {
                     Player {
                         name,
                         player_id: self.next_player_id,
-                        score: Score::Zero,
+                        score: 0,
+                        points: 0,
+                        presence: Presence::Connected,
+                        status: ActiveStatus::Active,
+                        profile: Profile::default(),
                     },
                 );
                 self_.player_ids.push(self.next_player_id);
@@ -87,24 +123,46 @@ impl Players {
         }
     }
 
+    /// Change the name of the provided player or observer. Names are subject to the same length
+    /// limit as in `add_player`, and must not be in use by anyone else.
+    pub fn rename_player(&self, player_id: PlayerID, name: String) -> Result<Self, PlayerError> {
+        if name.len() > 128 {
+            return Err(PlayerError::PlayerNameTooLong);
+        }
+        if self
+            .players
+            .values()
+            .chain(self.observers.iter())
+            .any(|p| p.name == name && p.player_id != player_id)
+        {
+            return Err(PlayerError::PlayerNameTaken);
+        }
+        let mut self_ = self.clone();
+        let p = match self_.players.get_mut(&player_id) {
+            Some(p) => p,
+            None => self_
+                .observers
+                .iter_mut()
+                .find(|o| o.player_id == player_id)
+                .ok_or(PlayerError::PlayerDoesntExist)?,
+        };
+        p.name = name;
+        Ok(self_)
+    }
+
     /// Remove a player from the game and from observation.
     pub fn remove_player(&self, player_id: PlayerID) -> Result<Self, PlayerError> {
-        let idx = self
-            .player_ids
-            .iter()
-            .position(|p| *p == player_id)
-            .ok_or(PlayerError::PlayerDoesntExist)?;
         let mut self_ = self.clone();
-        self_.player_ids.remove(idx);
-        self_.players.remove(&player_id);
-        if let Some(observer_idx) = self_
-            .observers
-            .iter()
-            .position(|p| p.player_id == player_id)
-        {
+        if self_.players.remove(&player_id).is_some() {
+            self_.player_ids.retain(|p| *p != player_id);
+        } else {
+            let observer_idx = self_
+                .observers
+                .iter()
+                .position(|p| p.player_id == player_id)
+                .ok_or(PlayerError::PlayerDoesntExist)?;
             self_.observers.remove(observer_idx);
         }
-
         Ok(self_)
     }
 
@@ -128,6 +186,15 @@ impl Players {
         }
     }
 
+    /// Randomize the play order, e.g. to seat players before the game starts. The order only
+    /// depends on `rng`, so a seeded `rng` always produces the same seating.
+    #[must_use]
+    pub fn shuffle_order<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        let mut self_ = self.clone();
+        self_.player_ids.shuffle(rng);
+        self_
+    }
+
     /// Convert the provided `player_id` into an observer rather than a player.
     pub fn make_player_into_observer(&self, player_id: PlayerID) -> Result<Self, PlayerError> {
         let mut self_ = self.clone();
@@ -154,32 +221,25 @@ impl Players {
         Ok(self_)
     }
 
-    /// Increment the score for the provided player. If a player just won the
-    /// game, returns the winning player as well.
+    /// Increment the score for the provided player. If that brings them to `wins_required`, they
+    /// have won the game and are returned as well.
     pub fn increment_score(
         &self,
         player_id: PlayerID,
+        wins_required: u8,
     ) -> Result<(Self, Option<PlayerID>), PlayerError> {
         let mut self_ = self.clone();
-        let num_winners = self_
-            .players
-            .values()
-            .map(|p| p.score)
-            .filter(|s| *s == Score::WonGame)
-            .count();
-        let mut p = self_
+        // Before declaring victory, make sure nobody else has already declared victory.
+        if self_.players.values().any(|p| p.score >= wins_required) {
+            return Err(PlayerError::PlayerAlreadyWon);
+        }
+        let p = self_
             .players
             .get_mut(&player_id)
             .ok_or(PlayerError::PlayerDoesntExist)?;
+        p.score += 1;
 
-        p.score = match p.score {
-            Score::Zero => Score::WonOne,
-            // Before declaring victory, make sure nobody else has already declared victory.
-            Score::WonOne if num_winners == 0 => Score::WonGame,
-            Score::WonOne | Score::WonGame => return Err(PlayerError::PlayerAlreadyWon),
-        };
-
-        let winning_player_id = if p.score == Score::WonGame {
+        let winning_player_id = if p.score >= wins_required {
             Some(p.player_id)
         } else {
             None
@@ -188,27 +248,118 @@ impl Players {
         Ok((self_, winning_player_id))
     }
 
-    /// Reset all scores (for players and observers) to zero.
-    pub fn reset_all_scores(&self) -> Self {
+    /// Record whether the provided player or observer is connected.
+    pub fn set_presence(
+        &self,
+        player_id: PlayerID,
+        presence: Presence,
+    ) -> Result<Self, PlayerError> {
         let mut self_ = self.clone();
-        for p in self_.players.values_mut() {
-            p.score = Score::Zero;
+        let p = match self_.players.get_mut(&player_id) {
+            Some(p) => p,
+            None => self_
+                .observers
+                .iter_mut()
+                .find(|o| o.player_id == player_id)
+                .ok_or(PlayerError::PlayerDoesntExist)?,
+        };
+        p.presence = presence;
+        Ok(self_)
+    }
+
+    /// Replace the cosmetic details of the provided player or observer. Returns
+    /// `ProfileTooLong` if any field is over its length limit.
+    pub fn update_profile(
+        &self,
+        player_id: PlayerID,
+        profile: Profile,
+    ) -> Result<Self, PlayerError> {
+        if !profile.is_valid() {
+            return Err(PlayerError::ProfileTooLong);
         }
-        for o in self_.observers.iter_mut() {
-            o.score = Score::Zero;
+        let mut self_ = self.clone();
+        let p = match self_.players.get_mut(&player_id) {
+            Some(p) => p,
+            None => self_
+                .observers
+                .iter_mut()
+                .find(|o| o.player_id == player_id)
+                .ok_or(PlayerError::PlayerDoesntExist)?,
+        };
+        p.profile = profile;
+        Ok(self_)
+    }
+
+    /// Record whether the provided player is still in the running.
+    pub fn set_status(
+        &self,
+        player_id: PlayerID,
+        status: ActiveStatus,
+    ) -> Result<Self, PlayerError> {
+        let mut self_ = self.clone();
+        self_
+            .players
+            .get_mut(&player_id)
+            .ok_or(PlayerError::PlayerDoesntExist)?
+            .status = status;
+        Ok(self_)
+    }
+
+    /// Add `points` to the provided player's total.
+    pub fn award_points(&self, player_id: PlayerID, points: u32) -> Result<Self, PlayerError> {
+        let mut self_ = self.clone();
+        let p = self_
+            .players
+            .get_mut(&player_id)
+            .ok_or(PlayerError::PlayerDoesntExist)?;
+        p.points = p.points.saturating_add(points);
+        Ok(self_)
+    }
+
+    /// The player with the most points, preferring whoever is first in play order on ties.
+    pub fn points_leader(&self) -> Option<PlayerID> {
+        self.players()
+            .min_by_key(|p| std::cmp::Reverse(p.points))
+            .map(|p| p.player_id)
+    }
+
+    /// Reset all scores and points (for players and observers) to zero, and make everyone active
+    /// again.
+    pub fn reset_all_scores(&self) -> Self {
+        let mut self_ = self.clone();
+        for p in self_.players.values_mut().chain(self_.observers.iter_mut()) {
+            p.score = 0;
+            p.points = 0;
+            p.status = ActiveStatus::Active;
         }
         self_
     }
 }
 
-#[derive(Error, Debug, Deserialize, Serialize, PartialEq, Eq)]
+impl Default for Players {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum PlayerError {
     #[error("Player does not exist")]
     PlayerDoesntExist,
     #[error("Need at least two players to start the game")]
     NotEnoughPlayers,
+    #[error("The game is full")]
+    TooManyPlayers,
+    #[error("Not every player is ready")]
+    PlayersNotReady,
     #[error("Player name is too long")]
     PlayerNameTooLong,
+    #[error("Another player already has that name")]
+    PlayerNameTaken,
+    #[error("Profile field is too long")]
+    ProfileTooLong,
     #[error("Reordered player IDs don't match existing")]
     MismatchedPlayerIDs,
     #[error("Player has already won the game!")]
@@ -217,6 +368,132 @@ pub enum PlayerError {
 
 #[cfg(test)]
 mod tests {
-    use super::super::{Player, PlayerID, Score};
-    use super::Players;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{PlayerError, Players};
+    use crate::types::{ActiveStatus, PlayerID, Profile};
+
+    #[test]
+    pub fn test_iter_from() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let iter_from = |player_id| players.iter_from(player_id).collect::<Vec<_>>();
+        assert_eq!(iter_from(p1), vec![p1, p2, p3]);
+        assert_eq!(iter_from(p2), vec![p2, p3, p1]);
+        assert_eq!(iter_from(p3), vec![p3, p1, p2]);
+        assert_eq!(iter_from(PlayerID(42)), vec![p1, p2, p3]);
+        assert_eq!(Players::new().iter_from(p1).next(), None);
+    }
+
+    #[test]
+    pub fn test_next_active_player() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let (players, p3) = players.add_player("p3".to_string()).unwrap();
+        let (players, p4) = players.add_player("p4".to_string()).unwrap();
+        let players = players
+            .set_status(p2, ActiveStatus::Eliminated)
+            .unwrap()
+            .make_player_into_observer(p3)
+            .unwrap();
+        let next = |player_id| players.next_active_player(player_id).map(|p| p.player_id());
+        assert_eq!(next(p1), Some(p4));
+        assert_eq!(next(p2), Some(p4));
+        assert_eq!(next(p4), Some(p1));
+        assert_eq!(next(p3), None);
+        assert_eq!(
+            players.player(p2).unwrap().status(),
+            ActiveStatus::Eliminated
+        );
+        assert_eq!(
+            players
+                .reset_all_scores()
+                .next_active_player(p1)
+                .map(|p| p.player_id()),
+            Some(p2)
+        );
+    }
+
+    #[test]
+    pub fn test_rename_player() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let players = players.make_player_into_observer(p2).unwrap();
+
+        let players = players.rename_player(p1, "Alice".to_string()).unwrap();
+        assert_eq!(players.player(p1).unwrap().name(), "Alice");
+        assert_eq!(players.add_player("Alice".to_string()).unwrap().1, p1);
+        assert_eq!(
+            players.rename_player(p1, "Alice".to_string()),
+            Ok(players.clone())
+        );
+        let players = players.rename_player(p2, "Bob".to_string()).unwrap();
+        assert_eq!(players.observers().next().unwrap().name(), "Bob");
+
+        assert_eq!(
+            players.rename_player(p1, "Bob".to_string()),
+            Err(PlayerError::PlayerNameTaken)
+        );
+        assert_eq!(
+            players.rename_player(p1, "a".repeat(129)),
+            Err(PlayerError::PlayerNameTooLong)
+        );
+        assert_eq!(
+            players.rename_player(PlayerID(42), "Carol".to_string()),
+            Err(PlayerError::PlayerDoesntExist)
+        );
+    }
+
+    #[test]
+    pub fn test_update_profile() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let players = players.make_player_into_observer(p2).unwrap();
+        let profile = Profile {
+            avatar: Some("https://example.com/p1.png".to_string()),
+            color: Some("#ff8800".to_string()),
+            tagline: None,
+        };
+        let players = players.update_profile(p1, profile.clone()).unwrap();
+        assert_eq!(players.player(p1).unwrap().profile(), &profile);
+        let players = players.update_profile(p2, profile.clone()).unwrap();
+        assert_eq!(players.observers().next().unwrap().profile(), &profile);
+
+        let too_long = Profile {
+            tagline: Some("a".repeat(129)),
+            ..profile
+        };
+        assert_eq!(
+            players.update_profile(p1, too_long),
+            Err(PlayerError::ProfileTooLong)
+        );
+        assert_eq!(
+            players.update_profile(PlayerID(42), Profile::default()),
+            Err(PlayerError::PlayerDoesntExist)
+        );
+    }
+
+    #[test]
+    pub fn test_shuffle_order() {
+        let players = (1..=6).fold(Players::new(), |players, i| {
+            players.add_player(format!("p{}", i)).unwrap().0
+        });
+        let shuffled = players.shuffle_order(&mut StdRng::seed_from_u64(1));
+        assert_eq!(
+            shuffled,
+            players.shuffle_order(&mut StdRng::seed_from_u64(1))
+        );
+        let mut sorted = shuffled.player_ids().to_vec();
+        sorted.sort_by_key(|p| p.0);
+        assert_eq!(sorted, players.player_ids());
+        assert_ne!(
+            (0..10)
+                .map(|seed| players.shuffle_order(&mut StdRng::seed_from_u64(seed)))
+                .filter(|p| p.player_ids() != players.player_ids())
+                .count(),
+            0
+        );
+    }
 }
@@ -0,0 +1,261 @@
+//! Projections of the game state which only contain what a particular player is allowed to see,
+//! and are therefore safe to send over the wire.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::game_states::bidding::Bid;
+use crate::types::{Card, Hand, PlayerID, Players};
+
+/// A card as seen by a particular player.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CardView {
+    /// A face-down card which the viewer doesn't know.
+    Hidden,
+    /// A card which the viewer knows, either because they placed it or because it was flipped.
+    Known(Card),
+}
+
+/// What the viewer can see of one player's cards.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeatView {
+    pub player_id: PlayerID,
+    /// The player's face-down stack, bottom card first.
+    pub stack: Vec<CardView>,
+    /// The cards which have been flipped from the player's stack during the current challenge.
+    pub revealed: Vec<Card>,
+    /// The number of cards remaining in the player's hand.
+    pub hand_size: usize,
+}
+
+/// The phase-specific public information.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum PhaseView {
+    Initialize {
+        /// The players who are ready to start, in play order.
+        ready: Vec<PlayerID>,
+    },
+    Placement {
+        current_player: PlayerID,
+    },
+    Bidding {
+        current_player: PlayerID,
+        bids: HashMap<PlayerID, Bid>,
+    },
+    Selection {
+        selector: PlayerID,
+        goal: u8,
+        found: u8,
+    },
+    Discard {
+        selector: PlayerID,
+        chooser: PlayerID,
+        /// The selector's cards in discard order. Only the selector can see what they are.
+        selector_cards: Vec<CardView>,
+    },
+    GameOver {
+        winner: PlayerID,
+    },
+}
+
+/// How much of the game an observer can see.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Visibility {
+    /// Only what every player at the table can see, e.g. for live spectators.
+    Public,
+    /// Every card, e.g. for post-game review or debugging.
+    FullReveal,
+}
+
+/// Everything a single player is allowed to know about the game.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlayerView {
+    pub viewer: PlayerID,
+    pub players: Players,
+    /// The viewer's own hand, if they have one.
+    pub hand: Option<Hand>,
+    /// Every player's cards, in play order.
+    pub seats: Vec<SeatView>,
+    pub phase: PhaseView,
+}
+
+impl PlayerView {
+    pub(crate) fn new(viewer: PlayerID, table: Table) -> Self {
+        Self {
+            viewer,
+            players: table.players,
+            hand: table.hands.get(&viewer).copied(),
+            seats: table.seats,
+            phase: table.phase,
+        }
+    }
+}
+
+/// The game as seen by someone who isn't playing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ObserverView {
+    pub visibility: Visibility,
+    pub players: Players,
+    /// Every player's hand. Empty unless `visibility` is `FullReveal`.
+    pub hands: HashMap<PlayerID, Hand>,
+    /// Every player's cards, in play order.
+    pub seats: Vec<SeatView>,
+    pub phase: PhaseView,
+}
+
+impl ObserverView {
+    pub(crate) fn new(visibility: Visibility, table: Table) -> Self {
+        let hands = match visibility {
+            Visibility::Public => HashMap::new(),
+            Visibility::FullReveal => table.hands,
+        };
+        Self {
+            visibility,
+            players: table.players,
+            hands,
+            seats: table.seats,
+            phase: table.phase,
+        }
+    }
+}
+
+/// Who a view is being built for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Perspective {
+    Player(PlayerID),
+    Observer(Visibility),
+}
+
+impl Perspective {
+    /// Whether the face-down cards belonging to `owner` are visible from this perspective.
+    pub(crate) fn can_see(self, owner: PlayerID) -> bool {
+        match self {
+            Perspective::Player(viewer) => viewer == owner,
+            Perspective::Observer(Visibility::Public) => false,
+            Perspective::Observer(Visibility::FullReveal) => true,
+        }
+    }
+}
+
+/// The parts of a view shared by players and observers, with face-down cards already redacted.
+/// Hands are not redacted; it's up to the final view to pick which ones to keep.
+pub(crate) struct Table {
+    players: Players,
+    hands: HashMap<PlayerID, Hand>,
+    seats: Vec<SeatView>,
+    phase: PhaseView,
+}
+
+impl Table {
+    pub(crate) fn new(
+        perspective: Perspective,
+        players: &Players,
+        hands: &HashMap<PlayerID, Hand>,
+        cards: &HashMap<PlayerID, Vec<Card>>,
+        revealed: &HashMap<PlayerID, Vec<Card>>,
+        phase: PhaseView,
+    ) -> Self {
+        let seats = players
+            .player_ids()
+            .iter()
+            .map(|player_id| SeatView {
+                player_id: *player_id,
+                stack: cards
+                    .get(player_id)
+                    .into_iter()
+                    .flatten()
+                    .map(|card| {
+                        if perspective.can_see(*player_id) {
+                            CardView::Known(*card)
+                        } else {
+                            CardView::Hidden
+                        }
+                    })
+                    .collect(),
+                revealed: revealed.get(player_id).cloned().unwrap_or_default(),
+                hand_size: hands.get(player_id).map(|h| h.num_cards()).unwrap_or(0),
+            })
+            .collect();
+        Self {
+            players: players.clone(),
+            hands: hands.clone(),
+            seats,
+            phase,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CardView, PhaseView, Visibility};
+    use crate::game::{Action, Game};
+    use crate::types::{Card, Players};
+
+    #[test]
+    pub fn test_view_hides_other_players_cards() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let game = Game::new(players).apply(p1, Action::StartGame).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+
+        let view = game.view_for(p1);
+        assert_eq!(view.hand.unwrap().num_cards(), 3);
+        assert_eq!(view.seats[0].stack, vec![CardView::Known(Card::Flower)]);
+        assert_eq!(view.seats[1].stack, vec![CardView::Hidden]);
+        assert_eq!(view.seats[1].hand_size, 3);
+        assert_eq!(view.phase, PhaseView::Placement { current_player: p1 });
+
+        let game = game.apply(p1, Action::Bid(2)).unwrap().0;
+        let game = game.apply(p2, Action::Pass).unwrap().0;
+        let game = game.apply(p1, Action::Flip(p1)).unwrap().0;
+        let view = game.view_for(p2);
+        assert_eq!(view.seats[0].stack, vec![]);
+        assert_eq!(view.seats[0].revealed, vec![Card::Flower]);
+        assert_eq!(view.seats[1].stack, vec![CardView::Known(Card::Skull)]);
+
+        // p2 picks which of p1's cards to discard without seeing them.
+        let game = game.apply(p1, Action::Flip(p2)).unwrap().0;
+        match game.view_for(p2).phase {
+            PhaseView::Discard { selector_cards, .. } => {
+                assert_eq!(selector_cards, vec![CardView::Hidden; 4])
+            }
+            p => panic!("unexpected phase {:?}", p),
+        }
+        match game.view_for(p1).phase {
+            PhaseView::Discard { selector_cards, .. } => {
+                assert!(selector_cards.contains(&CardView::Known(Card::Skull)))
+            }
+            p => panic!("unexpected phase {:?}", p),
+        }
+    }
+
+    #[test]
+    pub fn test_observer_visibility() {
+        let (players, p1) = Players::new().add_player("p1".to_string()).unwrap();
+        let (players, p2) = players.add_player("p2".to_string()).unwrap();
+        let game = Game::new(players).apply(p1, Action::StartGame).unwrap().0;
+        let game = game.apply(p1, Action::PlaceCard(Card::Flower)).unwrap().0;
+        let game = game.apply(p2, Action::PlaceCard(Card::Skull)).unwrap().0;
+
+        let public = game.observer_view(Visibility::Public);
+        assert!(public.hands.is_empty());
+        assert_eq!(public.seats[0].stack, vec![CardView::Hidden]);
+        assert_eq!(public.seats[1].stack, vec![CardView::Hidden]);
+        assert_eq!(public.seats[1].hand_size, 3);
+
+        let full = game.observer_view(Visibility::FullReveal);
+        assert_eq!(full.hands[&p2].num_skulls(), 0);
+        assert_eq!(full.seats[0].stack, vec![CardView::Known(Card::Flower)]);
+        assert_eq!(full.seats[1].stack, vec![CardView::Known(Card::Skull)]);
+    }
+}